@@ -1,23 +1,10 @@
-extern crate dotenv;
-extern crate env_logger;
-extern crate futures;
-extern crate pb_async;
-extern crate tokio;
-
-use futures::Future;
-
-fn main() {
+#[tokio::main]
+async fn main() {
     env_logger::init();
 
     let token = dotenv::var("PUSHBULLET_TOKEN").expect("expected PUSHBULLET_TOKEN env var");
 
-    tokio::run(futures::lazy(move || {
-        let client = pb_async::Client::new(&token).unwrap();
-        tokio::executor::spawn(client.get_user().then(|res| {
-            println!("User information: {:#?}", res.unwrap());
-            Ok(())
-        }));
-
-        Ok(())
-    }))
+    let client = pb_async::Client::new(&token).unwrap();
+    let user = client.get_user().await.unwrap();
+    println!("User information: {:#?}", user);
 }
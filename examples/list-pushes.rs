@@ -0,0 +1,22 @@
+use futures_util::{StreamExt, TryStreamExt};
+
+#[tokio::main]
+async fn main() {
+    env_logger::init();
+
+    let token = dotenv::var("PUSHBULLET_TOKEN").expect("expected PUSHBULLET_TOKEN env var");
+
+    let client = pb_async::Client::new(&token).unwrap();
+    let params = pb_async::ListPushes {
+        active: Some(true),
+        limit: Some(20),
+        ..Default::default()
+    };
+    let pushes: Vec<_> = client
+        .all_pushes(params)
+        .take(50)
+        .try_collect()
+        .await
+        .unwrap();
+    println!("Pushes: {:#?}", pushes);
+}
@@ -0,0 +1,17 @@
+use futures_util::StreamExt;
+
+#[tokio::main]
+async fn main() {
+    env_logger::init();
+
+    let token = dotenv::var("PUSHBULLET_TOKEN").expect("expected PUSHBULLET_TOKEN env var");
+
+    let client = pb_async::Client::new(&token).unwrap();
+    let mut events = Box::pin(client.stream());
+    while let Some(event) = events.next().await {
+        match event {
+            Ok(event) => println!("Event: {:#?}", event),
+            Err(e) => panic!("error: {}", e),
+        }
+    }
+}
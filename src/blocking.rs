@@ -0,0 +1,516 @@
+//! Blocking PushBullet client, for programs which don't otherwise use
+//! `tokio`.
+//!
+//! Only available with the `blocking` feature enabled.
+//!
+//! [`Client`] has the same methods as the asynchronous [`crate::Client`],
+//! but each runs the request to completion on an internal runtime before
+//! returning. Streams are returned as iterators, which block until the next
+//! item is available. [`OAuthClient`] likewise mirrors
+//! [`crate::OAuthClient`].
+//!
+//! The blocking client must not be used from within an asynchronous
+//! runtime - doing so will panic.
+//!
+//! Example usage:
+//!
+//! ```no_run
+//! let client = pb_async::blocking::Client::new("...").unwrap();
+//!
+//! let result = client.push(
+//!     pb_async::PushTarget::SelfUser {},
+//!     pb_async::PushData::Note {
+//!         title: "",
+//!         body: "Hello, user!",
+//!     },
+//! );
+//! if let Err(error) = result {
+//!     eprintln!("error: {}", error);
+//! }
+//! ```
+use std::future::Future;
+use std::io::Read;
+use std::sync::Arc;
+
+use bytes::Bytes;
+use futures_util::stream::{self, Stream, StreamExt};
+use tokio::runtime::Runtime;
+
+use crate::{
+    AccessToken, ChannelInfo, ChannelParams, Chat, Device, DeviceParams, Encryption, Event, Grant,
+    ListPushes, OAuthClientInfo, OwnedChannel, Push, PushData, PushPage, PushTarget, PushUpdate,
+    RateLimit, RequestError, ResponseType, SmsMessage, SmsThread, StartupError, StreamError,
+    Subscription, SyncEvent, Text, TextData, UploadRequestResponse, User,
+};
+
+/// Size of the chunks read from files in [`Client::upload_request_stream`].
+const UPLOAD_CHUNK_SIZE: usize = 64 * 1024;
+
+/// Blocking PushBullet client.
+///
+/// Cloning the client is cheap, and clones share the same connection pool
+/// and runtime.
+#[derive(Clone)]
+pub struct Client {
+    inner: crate::Client,
+    runtime: Arc<Runtime>,
+}
+
+impl Client {
+    /// Create a new client with a given token.
+    ///
+    /// Example usage:
+    ///
+    /// ```no_run
+    /// let token = std::env::var("PB_TOKEN").expect("expected PB_TOKEN env var to exist");
+    /// let client = pb_async::blocking::Client::new(&token)
+    ///     .expect("expected client creation to succeed");
+    /// ```
+    pub fn new(token: &str) -> Result<Self, StartupError> {
+        Client::from_client(crate::Client::new(token)?)
+    }
+
+    /// Create a blocking client from an existing asynchronous client.
+    ///
+    /// Use this with [`crate::Client::builder`] to configure the client.
+    ///
+    /// Example usage:
+    ///
+    /// ```no_run
+    /// use std::time::Duration;
+    ///
+    /// let client = pb_async::Client::builder("...")
+    ///     .timeout(Duration::from_secs(30))
+    ///     .build()
+    ///     .expect("expected client creation to succeed");
+    /// let client = pb_async::blocking::Client::from_client(client)
+    ///     .expect("expected runtime creation to succeed");
+    /// ```
+    pub fn from_client(client: crate::Client) -> Result<Self, StartupError> {
+        Ok(Client {
+            inner: client,
+            runtime: runtime()?,
+        })
+    }
+
+    /// Retrieves the asynchronous client used to make requests.
+    pub fn async_client(&self) -> &crate::Client {
+        &self.inner
+    }
+
+    /// Sets the key used for end-to-end encryption, or disables end-to-end
+    /// encryption if `None`.
+    ///
+    /// See [`crate::Client::set_encryption`].
+    pub fn set_encryption(&mut self, encryption: Option<Encryption>) {
+        self.inner.set_encryption(encryption);
+    }
+
+    /// Retrieves the rate limit state reported by the server in response to
+    /// the latest request.
+    ///
+    /// See [`crate::Client::rate_limit`].
+    pub fn rate_limit(&self) -> Option<RateLimit> {
+        self.inner.rate_limit()
+    }
+
+    /// Retrieves information of the logged in user.
+    ///
+    /// See [`crate::Client::get_user`].
+    pub fn get_user(&self) -> Result<User, RequestError> {
+        self.block_on(self.inner.get_user())
+    }
+
+    /// Retrieves a list of devices.
+    ///
+    /// See [`crate::Client::list_devices`].
+    pub fn list_devices(&self) -> Result<Vec<Device>, RequestError> {
+        self.block_on(self.inner.list_devices())
+    }
+
+    /// Creates a new device.
+    ///
+    /// See [`crate::Client::create_device`].
+    pub fn create_device(&self, params: &DeviceParams<'_>) -> Result<Device, RequestError> {
+        self.block_on(self.inner.create_device(params))
+    }
+
+    /// Updates an existing device.
+    ///
+    /// See [`crate::Client::update_device`].
+    pub fn update_device(
+        &self,
+        iden: &str,
+        params: &DeviceParams<'_>,
+    ) -> Result<Device, RequestError> {
+        self.block_on(self.inner.update_device(iden, params))
+    }
+
+    /// Deletes a device.
+    ///
+    /// See [`crate::Client::delete_device`].
+    pub fn delete_device(&self, iden: &str) -> Result<(), RequestError> {
+        self.block_on(self.inner.delete_device(iden))
+    }
+
+    /// Pushes some data to a target.
+    ///
+    /// See [`crate::Client::push`].
+    pub fn push(&self, target: PushTarget<'_>, data: PushData<'_>) -> Result<Push, RequestError> {
+        self.block_on(self.inner.push(target, data))
+    }
+
+    /// Uploads a file prior to pushing it via [`Client::push`].
+    ///
+    /// See [`crate::Client::upload_request`].
+    pub fn upload_request(
+        &self,
+        file_name: &str,
+        file_type: &str,
+        upload_data: impl Into<Bytes>,
+    ) -> Result<UploadRequestResponse, RequestError> {
+        self.block_on(self.inner.upload_request(file_name, file_type, upload_data))
+    }
+
+    /// Uploads a file prior to pushing it via [`Client::push`], reading its
+    /// contents from `reader` as they are sent.
+    ///
+    /// The file never needs to be kept in memory in full. See
+    /// [`crate::Client::upload_request_stream`].
+    ///
+    /// Example usage:
+    ///
+    /// ```no_run
+    /// let client = pb_async::blocking::Client::new("...").unwrap();
+    ///
+    /// let file = std::fs::File::open("report.pdf").unwrap();
+    /// let file_data = client
+    ///     .upload_request_stream("report.pdf", "application/pdf", file)
+    ///     .unwrap();
+    /// println!("Uploaded to {}", file_data.file_url);
+    /// ```
+    pub fn upload_request_stream(
+        &self,
+        file_name: &str,
+        file_type: &str,
+        reader: impl Read + Send + 'static,
+    ) -> Result<UploadRequestResponse, RequestError> {
+        // reads block the runtime, but it only drives this client's requests
+        let chunks = stream::unfold(Some(reader), |reader| async move {
+            let mut reader = reader?;
+            let mut buf = vec![0; UPLOAD_CHUNK_SIZE];
+            match reader.read(&mut buf) {
+                Ok(0) => None,
+                Ok(read) => {
+                    buf.truncate(read);
+                    Some((Ok(Bytes::from(buf)), Some(reader)))
+                }
+                // the upload is aborted, so stop reading
+                Err(e) => Some((Err(e), None)),
+            }
+        });
+        self.block_on(self.inner.upload_request_stream(file_name, file_type, chunks))
+    }
+
+    /// Retrieves a single page of push history.
+    ///
+    /// See [`crate::Client::list_pushes`].
+    pub fn list_pushes(&self, params: &ListPushes) -> Result<PushPage, RequestError> {
+        self.block_on(self.inner.list_pushes(params))
+    }
+
+    /// Retrieves all push history matching the given parameters.
+    ///
+    /// Pages are requested lazily as the iterator is advanced. See
+    /// [`crate::Client::all_pushes`].
+    pub fn all_pushes(
+        &self,
+        params: ListPushes,
+    ) -> impl Iterator<Item = Result<Push, RequestError>> + 'static {
+        self.iter(self.inner.all_pushes(params))
+    }
+
+    /// Updates a push.
+    ///
+    /// See [`crate::Client::update_push`].
+    pub fn update_push(&self, iden: &str, update: &PushUpdate) -> Result<Push, RequestError> {
+        self.block_on(self.inner.update_push(iden, update))
+    }
+
+    /// Marks a push as dismissed.
+    ///
+    /// See [`crate::Client::dismiss_push`].
+    pub fn dismiss_push(&self, iden: &str) -> Result<Push, RequestError> {
+        self.block_on(self.inner.dismiss_push(iden))
+    }
+
+    /// Deletes a push.
+    ///
+    /// See [`crate::Client::delete_push`].
+    pub fn delete_push(&self, iden: &str) -> Result<(), RequestError> {
+        self.block_on(self.inner.delete_push(iden))
+    }
+
+    /// Deletes all of the user's pushes.
+    ///
+    /// See [`crate::Client::delete_all_pushes`].
+    pub fn delete_all_pushes(&self) -> Result<(), RequestError> {
+        self.block_on(self.inner.delete_all_pushes())
+    }
+
+    /// Retrieves a list of chats.
+    ///
+    /// See [`crate::Client::list_chats`].
+    pub fn list_chats(&self) -> Result<Vec<Chat>, RequestError> {
+        self.block_on(self.inner.list_chats())
+    }
+
+    /// Creates a chat with a given email address.
+    ///
+    /// See [`crate::Client::create_chat`].
+    pub fn create_chat(&self, email: &str) -> Result<Chat, RequestError> {
+        self.block_on(self.inner.create_chat(email))
+    }
+
+    /// Mutes or unmutes a chat.
+    ///
+    /// See [`crate::Client::update_chat`].
+    pub fn update_chat(&self, iden: &str, muted: bool) -> Result<Chat, RequestError> {
+        self.block_on(self.inner.update_chat(iden, muted))
+    }
+
+    /// Deletes a chat.
+    ///
+    /// See [`crate::Client::delete_chat`].
+    pub fn delete_chat(&self, iden: &str) -> Result<(), RequestError> {
+        self.block_on(self.inner.delete_chat(iden))
+    }
+
+    /// Retrieves a list of channel subscriptions.
+    ///
+    /// See [`crate::Client::list_subscriptions`].
+    pub fn list_subscriptions(&self) -> Result<Vec<Subscription>, RequestError> {
+        self.block_on(self.inner.list_subscriptions())
+    }
+
+    /// Subscribes to a channel by tag.
+    ///
+    /// See [`crate::Client::create_subscription`].
+    pub fn create_subscription(&self, channel_tag: &str) -> Result<Subscription, RequestError> {
+        self.block_on(self.inner.create_subscription(channel_tag))
+    }
+
+    /// Mutes or unmutes a channel subscription.
+    ///
+    /// See [`crate::Client::update_subscription`].
+    pub fn update_subscription(
+        &self,
+        iden: &str,
+        muted: bool,
+    ) -> Result<Subscription, RequestError> {
+        self.block_on(self.inner.update_subscription(iden, muted))
+    }
+
+    /// Unsubscribes from a channel.
+    ///
+    /// See [`crate::Client::delete_subscription`].
+    pub fn delete_subscription(&self, iden: &str) -> Result<(), RequestError> {
+        self.block_on(self.inner.delete_subscription(iden))
+    }
+
+    /// Retrieves information about a channel by tag.
+    ///
+    /// See [`crate::Client::channel_info`].
+    pub fn channel_info(&self, tag: &str) -> Result<ChannelInfo, RequestError> {
+        self.block_on(self.inner.channel_info(tag))
+    }
+
+    /// Retrieves a list of channels owned by the user.
+    ///
+    /// See [`crate::Client::list_channels`].
+    pub fn list_channels(&self) -> Result<Vec<OwnedChannel>, RequestError> {
+        self.block_on(self.inner.list_channels())
+    }
+
+    /// Creates a new channel owned by the user.
+    ///
+    /// See [`crate::Client::create_channel`].
+    pub fn create_channel(&self, params: &ChannelParams<'_>) -> Result<OwnedChannel, RequestError> {
+        self.block_on(self.inner.create_channel(params))
+    }
+
+    /// Sends an SMS message through a phone.
+    ///
+    /// See [`crate::Client::send_sms`].
+    pub fn send_sms(
+        &self,
+        device_iden: &str,
+        addresses: &[&str],
+        message: &str,
+    ) -> Result<Text, RequestError> {
+        self.block_on(self.inner.send_sms(device_iden, addresses, message))
+    }
+
+    /// Sends a text message through a phone, optionally with an attached
+    /// file.
+    ///
+    /// See [`crate::Client::create_text`].
+    pub fn create_text(
+        &self,
+        data: &TextData<'_>,
+        file_url: Option<&str>,
+    ) -> Result<Text, RequestError> {
+        self.block_on(self.inner.create_text(data, file_url))
+    }
+
+    /// Deletes a text message.
+    ///
+    /// See [`crate::Client::delete_text`].
+    pub fn delete_text(&self, iden: &str) -> Result<(), RequestError> {
+        self.block_on(self.inner.delete_text(iden))
+    }
+
+    /// Retrieves the SMS conversations on a phone.
+    ///
+    /// See [`crate::Client::list_sms_threads`].
+    pub fn list_sms_threads(&self, device: &Device) -> Result<Vec<SmsThread>, RequestError> {
+        self.block_on(self.inner.list_sms_threads(device))
+    }
+
+    /// Retrieves the messages in an SMS conversation on a phone.
+    ///
+    /// See [`crate::Client::list_sms_messages`].
+    pub fn list_sms_messages(
+        &self,
+        device: &Device,
+        thread_id: &str,
+    ) -> Result<Vec<SmsMessage>, RequestError> {
+        self.block_on(self.inner.list_sms_messages(device, thread_id))
+    }
+
+    /// Copies text to the clipboard of the user's other devices.
+    ///
+    /// See [`crate::Client::send_clipboard`].
+    pub fn send_clipboard(&self, body: &str, source_device_iden: &str) -> Result<(), RequestError> {
+        self.block_on(self.inner.send_clipboard(body, source_device_iden))
+    }
+
+    /// Dismisses a notification mirrored from another device.
+    ///
+    /// See [`crate::Client::dismiss_mirrored_notification`].
+    pub fn dismiss_mirrored_notification(
+        &self,
+        package_name: &str,
+        notification_id: &str,
+        notification_tag: Option<&str>,
+    ) -> Result<(), RequestError> {
+        self.block_on(self.inner.dismiss_mirrored_notification(
+            package_name,
+            notification_id,
+            notification_tag,
+        ))
+    }
+
+    /// Retrieves the OAuth clients the user has granted access to their
+    /// account.
+    ///
+    /// See [`crate::Client::list_grants`].
+    pub fn list_grants(&self) -> Result<Vec<Grant>, RequestError> {
+        self.block_on(self.inner.list_grants())
+    }
+
+    /// Retrieves the OAuth clients owned by the user.
+    ///
+    /// See [`crate::Client::list_clients`].
+    pub fn list_clients(&self) -> Result<Vec<OAuthClientInfo>, RequestError> {
+        self.block_on(self.inner.list_clients())
+    }
+
+    /// Connects to the realtime event stream.
+    ///
+    /// The connection is made when the iterator is first advanced. See
+    /// [`crate::Client::stream`].
+    pub fn stream(&self) -> impl Iterator<Item = Result<Event, StreamError>> + 'static {
+        self.iter(self.inner.stream())
+    }
+
+    /// Creates an iterator of changes to pushes and devices.
+    ///
+    /// The iterator never ends. See [`crate::Client::sync`].
+    pub fn sync(&self, modified_after: f64) -> impl Iterator<Item = SyncEvent> + 'static {
+        self.iter(self.inner.sync(modified_after))
+    }
+
+    fn block_on<F: Future>(&self, future: F) -> F::Output {
+        self.runtime.block_on(future)
+    }
+
+    /// Creates an iterator which blocks on each item of a stream.
+    fn iter<S>(&self, stream: S) -> impl Iterator<Item = S::Item> + 'static
+    where
+        S: Stream + 'static,
+    {
+        let runtime = self.runtime.clone();
+        let mut stream = Box::pin(stream);
+        std::iter::from_fn(move || runtime.block_on(stream.next()))
+    }
+}
+
+/// Blocking OAuth client, for acting on behalf of other PushBullet users.
+///
+/// See [`crate::OAuthClient`].
+pub struct OAuthClient {
+    inner: crate::OAuthClient,
+    runtime: Arc<Runtime>,
+}
+
+impl OAuthClient {
+    /// Create a new OAuth client with a given client id and secret.
+    ///
+    /// Example usage:
+    ///
+    /// ```no_run
+    /// let client = pb_async::blocking::OAuthClient::new("client-id", "client-secret")
+    ///     .expect("expected client creation to succeed");
+    /// ```
+    pub fn new(client_id: &str, client_secret: &str) -> Result<Self, StartupError> {
+        OAuthClient::from_client(crate::OAuthClient::new(client_id, client_secret)?)
+    }
+
+    /// Create a blocking OAuth client from an existing asynchronous OAuth
+    /// client.
+    pub fn from_client(client: crate::OAuthClient) -> Result<Self, StartupError> {
+        Ok(OAuthClient {
+            inner: client,
+            runtime: runtime()?,
+        })
+    }
+
+    /// Creates the URL to send a user to in order to authorize this client.
+    ///
+    /// See [`crate::OAuthClient::authorize_url`].
+    pub fn authorize_url(
+        &self,
+        redirect_uri: &str,
+        response_type: ResponseType,
+        state: Option<&str>,
+    ) -> String {
+        self.inner.authorize_url(redirect_uri, response_type, state)
+    }
+
+    /// Exchanges an authorization code for an access token.
+    ///
+    /// See [`crate::OAuthClient::exchange_code`].
+    pub fn exchange_code(&self, code: &str) -> Result<AccessToken, RequestError> {
+        self.runtime.block_on(self.inner.exchange_code(code))
+    }
+}
+
+/// Creates the runtime requests are run on.
+fn runtime() -> Result<Arc<Runtime>, StartupError> {
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .map_err(StartupError::Runtime)?;
+    Ok(Arc::new(runtime))
+}
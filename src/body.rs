@@ -0,0 +1,109 @@
+//! Bodies of requests sent by the client.
+use std::convert::Infallible;
+use std::error::Error;
+use std::fmt;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use bytes::Bytes;
+use futures_util::stream::{Stream, TryStreamExt};
+use http_body_util::Full;
+use hyper::body::{Body, Frame, SizeHint};
+
+type BoxError = Box<dyn Error + Send + Sync>;
+type BoxStream = Pin<Box<dyn Stream<Item = Result<Bytes, BoxError>> + Send>>;
+
+/// Body of a request sent by a [`Client`](crate::Client).
+///
+/// This only needs to be named when creating a hyper client to pass to
+/// [`ClientBuilder::hyper_client`](crate::ClientBuilder::hyper_client).
+pub struct RequestBody(Inner);
+
+enum Inner {
+    Full(Full<Bytes>),
+    Stream(BoxStream),
+}
+
+impl RequestBody {
+    /// Creates a body which is sent as the given stream produces data.
+    pub(crate) fn stream<S, E>(stream: S) -> Self
+    where
+        S: Stream<Item = Result<Bytes, E>> + Send + 'static,
+        E: Into<BoxError> + 'static,
+    {
+        RequestBody(Inner::Stream(Box::pin(stream.map_err(Into::into))))
+    }
+
+    /// Copies the body, so that the request can be sent again. Streamed
+    /// bodies can only be sent once, and return `None`.
+    pub(crate) fn try_clone(&self) -> Option<Self> {
+        match self.0 {
+            Inner::Full(ref full) => Some(RequestBody(Inner::Full(full.clone()))),
+            Inner::Stream(_) => None,
+        }
+    }
+}
+
+impl Body for RequestBody {
+    type Data = Bytes;
+    type Error = BoxError;
+
+    fn poll_frame(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Bytes>, BoxError>>> {
+        match self.get_mut().0 {
+            Inner::Full(ref mut full) => Pin::new(full)
+                .poll_frame(cx)
+                .map_err(|never: Infallible| match never {}),
+            Inner::Stream(ref mut stream) => stream.as_mut().poll_next(cx).map_ok(Frame::data),
+        }
+    }
+
+    fn is_end_stream(&self) -> bool {
+        match self.0 {
+            Inner::Full(ref full) => full.is_end_stream(),
+            Inner::Stream(_) => false,
+        }
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        match self.0 {
+            Inner::Full(ref full) => full.size_hint(),
+            Inner::Stream(_) => SizeHint::default(),
+        }
+    }
+}
+
+impl Default for RequestBody {
+    fn default() -> Self {
+        RequestBody(Inner::Full(Full::default()))
+    }
+}
+
+impl From<Bytes> for RequestBody {
+    fn from(bytes: Bytes) -> Self {
+        RequestBody(Inner::Full(Full::new(bytes)))
+    }
+}
+
+impl From<Vec<u8>> for RequestBody {
+    fn from(bytes: Vec<u8>) -> Self {
+        Bytes::from(bytes).into()
+    }
+}
+
+impl From<String> for RequestBody {
+    fn from(string: String) -> Self {
+        Bytes::from(string).into()
+    }
+}
+
+impl fmt::Debug for RequestBody {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            Inner::Full(ref full) => f.debug_tuple("RequestBody").field(full).finish(),
+            Inner::Stream(_) => f.write_str("RequestBody(<stream>)"),
+        }
+    }
+}
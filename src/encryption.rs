@@ -0,0 +1,259 @@
+//! End-to-end encryption of ephemerals and permanents.
+use std::fmt;
+use std::num::NonZeroU32;
+
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use ring::rand::SecureRandom;
+use ring::{aead, pbkdf2, rand};
+use serde::Serialize;
+
+use crate::EncryptionError;
+
+const ITERATIONS: NonZeroU32 = match NonZeroU32::new(30000) {
+    Some(iterations) => iterations,
+    None => unreachable!(),
+};
+const KEY_LEN: usize = 32;
+const TAG_LEN: usize = 16;
+const IV_LEN: usize = 12;
+const VERSION: u8 = b'1';
+
+/// End-to-end encryption key.
+///
+/// When end-to-end encryption is enabled in PushBullet, SMS and notification
+/// mirroring data is encrypted with a key derived from a password the user
+/// chooses. Configure a [`Client`] with [`Client::set_encryption`] to
+/// transparently decrypt this data in [`Client::stream`],
+/// [`Client::list_sms_threads`] and [`Client::list_sms_messages`], and to
+/// encrypt ephemerals sent with [`Client::send_clipboard`] and
+/// [`Client::dismiss_mirrored_notification`].
+///
+/// Example usage:
+///
+/// ```no_run
+/// # async fn example() -> Result<(), pb_async::RequestError> {
+/// let mut client = pb_async::Client::new("...").unwrap();
+///
+/// let user = client.get_user().await?;
+///
+/// client.set_encryption(Some(pb_async::Encryption::new("password", &user.iden)));
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [`Client`]: crate::Client
+/// [`Client::set_encryption`]: crate::Client::set_encryption
+/// [`Client::stream`]: crate::Client::stream
+/// [`Client::list_sms_threads`]: crate::Client::list_sms_threads
+/// [`Client::list_sms_messages`]: crate::Client::list_sms_messages
+/// [`Client::send_clipboard`]: crate::Client::send_clipboard
+/// [`Client::dismiss_mirrored_notification`]: crate::Client::dismiss_mirrored_notification
+pub struct Encryption {
+    key: [u8; KEY_LEN],
+}
+
+impl Encryption {
+    /// Derives the encryption key from the user's password.
+    ///
+    /// `user_iden` is [`User.iden`], from [`Client::get_user`].
+    ///
+    /// [`User.iden`]: crate::User::iden
+    /// [`Client::get_user`]: crate::Client::get_user
+    pub fn new(password: &str, user_iden: &str) -> Self {
+        let mut key = [0u8; KEY_LEN];
+        pbkdf2::derive(
+            pbkdf2::PBKDF2_HMAC_SHA256,
+            ITERATIONS,
+            user_iden.as_bytes(),
+            password.as_bytes(),
+            &mut key,
+        );
+        Encryption { key }
+    }
+
+    /// Encrypts a message, returning the encoded ciphertext.
+    pub fn encrypt(&self, message: &[u8]) -> String {
+        let mut iv = [0u8; IV_LEN];
+        rand::SystemRandom::new()
+            .fill(&mut iv)
+            .expect("expected system random number generator to succeed");
+
+        let mut encrypted_message = message.to_vec();
+        let tag = self
+            .key()
+            .seal_in_place_separate_tag(
+                aead::Nonce::assume_unique_for_key(iv),
+                aead::Aad::empty(),
+                &mut encrypted_message,
+            )
+            .expect("expected encryption to succeed");
+
+        let mut encoded = Vec::with_capacity(1 + TAG_LEN + IV_LEN + encrypted_message.len());
+        encoded.push(VERSION);
+        encoded.extend_from_slice(tag.as_ref());
+        encoded.extend_from_slice(&iv);
+        encoded.extend_from_slice(&encrypted_message);
+        BASE64.encode(&encoded)
+    }
+
+    /// Decrypts an encoded ciphertext, as created by [`Encryption::encrypt`]
+    /// or received from PushBullet.
+    pub fn decrypt(&self, ciphertext: &str) -> Result<Vec<u8>, EncryptionError> {
+        let decoded = BASE64.decode(ciphertext).map_err(EncryptionError::Base64)?;
+        if decoded.len() < 1 + TAG_LEN + IV_LEN {
+            return Err(EncryptionError::TooShort);
+        }
+        if decoded[0] != VERSION {
+            return Err(EncryptionError::UnsupportedVersion(decoded[0]));
+        }
+        let tag = &decoded[1..1 + TAG_LEN];
+        let iv = &decoded[1 + TAG_LEN..1 + TAG_LEN + IV_LEN];
+        let encrypted_message = &decoded[1 + TAG_LEN + IV_LEN..];
+
+        let nonce =
+            aead::Nonce::try_assume_unique_for_key(iv).map_err(|_| EncryptionError::Decryption)?;
+        let mut in_out = encrypted_message.to_vec();
+        in_out.extend_from_slice(tag);
+        let message_len = self
+            .key()
+            .open_in_place(nonce, aead::Aad::empty(), &mut in_out)
+            .map_err(|_| EncryptionError::Decryption)?
+            .len();
+        in_out.truncate(message_len);
+        Ok(in_out)
+    }
+
+    fn key(&self) -> aead::LessSafeKey {
+        let key = aead::UnboundKey::new(&aead::AES_256_GCM, &self.key)
+            .expect("expected key to be the correct length");
+        aead::LessSafeKey::new(key)
+    }
+
+    /// Encrypts a JSON value, returning it wrapped in the format PushBullet
+    /// expects.
+    pub(crate) fn encrypt_value(&self, value: &serde_json::Value) -> serde_json::Value {
+        #[derive(Serialize)]
+        struct Encrypted {
+            encrypted: bool,
+            ciphertext: String,
+        }
+        let message = serde_json::to_vec(value).unwrap();
+        serde_json::to_value(Encrypted {
+            encrypted: true,
+            ciphertext: self.encrypt(&message),
+        })
+        .unwrap()
+    }
+}
+
+impl fmt::Debug for Encryption {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Encryption").finish()
+    }
+}
+
+/// Retrieves the ciphertext from a JSON value, if it is encrypted.
+pub(crate) fn ciphertext(value: &serde_json::Value) -> Option<&str> {
+    let encrypted = value
+        .get("encrypted")
+        .and_then(|encrypted| encrypted.as_bool())
+        .unwrap_or(false);
+    if encrypted {
+        value.get("ciphertext").and_then(|ciphertext| ciphertext.as_str())
+    } else {
+        None
+    }
+}
+
+/// Decrypts a JSON value if it is encrypted, or returns it as-is if it isn't.
+pub(crate) fn decrypt_value(
+    value: serde_json::Value,
+    encryption: Option<&Encryption>,
+) -> Result<serde_json::Value, EncryptionError> {
+    let message = match ciphertext(&value) {
+        Some(ciphertext) => match encryption {
+            Some(encryption) => encryption.decrypt(ciphertext)?,
+            None => return Err(EncryptionError::NoKey),
+        },
+        None => return Ok(value),
+    };
+    serde_json::from_slice(&message).map_err(EncryptionError::Json)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encryption() -> Encryption {
+        Encryption::new("hunter2", "up0snaKOsn")
+    }
+
+    #[test]
+    fn derive_key() {
+        // example key from the PushBullet API documentation
+        assert_eq!(
+            BASE64.encode(encryption().key),
+            "1sW28zp7CWv5TtGjlQpDHHG4Cbr9v36fG5o4f74LsKg="
+        );
+    }
+
+    #[test]
+    fn decrypt_known() {
+        let ciphertext = "MWaPbqu7kJdynzE/16pu7dQAAQIDBAUGBwgJCgu6fGBALA==";
+        assert_eq!(encryption().decrypt(ciphertext).unwrap(), b"meow!");
+    }
+
+    #[test]
+    fn round_trip() {
+        let encryption = encryption();
+        let ciphertext = encryption.encrypt(b"Hello, world!");
+        assert_ne!(encryption.encrypt(b"Hello, world!"), ciphertext);
+        assert_eq!(encryption.decrypt(&ciphertext).unwrap(), b"Hello, world!");
+    }
+
+    #[test]
+    fn too_short() {
+        let ciphertext = BASE64.encode([VERSION; TAG_LEN + IV_LEN]);
+        match encryption().decrypt(&ciphertext) {
+            Err(EncryptionError::TooShort) => {}
+            other => panic!("expected too short error, found {:?}", other),
+        }
+    }
+
+    #[test]
+    fn unsupported_version() {
+        let mut decoded = BASE64.decode(encryption().encrypt(b"meow!")).unwrap();
+        decoded[0] = b'2';
+        match encryption().decrypt(&BASE64.encode(decoded)) {
+            Err(EncryptionError::UnsupportedVersion(b'2')) => {}
+            other => panic!("expected unsupported version error, found {:?}", other),
+        }
+    }
+
+    #[test]
+    fn wrong_password() {
+        let ciphertext = encryption().encrypt(b"meow!");
+        match Encryption::new("hunter3", "up0snaKOsn").decrypt(&ciphertext) {
+            Err(EncryptionError::Decryption) => {}
+            other => panic!("expected decryption error, found {:?}", other),
+        }
+    }
+
+    #[test]
+    fn decrypt_values() {
+        let encryption = encryption();
+        let value = serde_json::json!({"type": "clip", "body": "copied"});
+        let encrypted = encryption.encrypt_value(&value);
+        assert_eq!(encrypted["encrypted"], true);
+        assert_eq!(
+            decrypt_value(encrypted.clone(), Some(&encryption)).unwrap(),
+            value
+        );
+        assert_eq!(decrypt_value(value.clone(), None).unwrap(), value);
+        match decrypt_value(encrypted, None) {
+            Err(EncryptionError::NoKey) => {}
+            other => panic!("expected no key error, found {:?}", other),
+        }
+    }
+}
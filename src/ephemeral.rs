@@ -0,0 +1,324 @@
+//! Ephemeral messages, sent between devices without being stored.
+use serde::{Deserialize, Serialize};
+
+use crate::{Client, RequestError};
+
+impl Client {
+    /// Copies text to the clipboard of the user's other devices.
+    ///
+    /// `source_device_iden` is the device the text is being copied from -
+    /// devices won't update their own clipboard with a clip they sent.
+    ///
+    /// Clips must include the user's identifier, so the first ephemeral sent
+    /// by a client retrieves the current user with [`Client::get_user`].
+    /// The identifier is then reused by the client and its clones.
+    ///
+    /// Example usage:
+    ///
+    /// ```no_run
+    /// # async fn run() -> Result<(), pb_async::RequestError> {
+    /// # let client = pb_async::Client::new("...").unwrap();
+    /// client
+    ///     .send_clipboard("cargo test --all", "ujpah72o0sjAoRtnM0jc")
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn send_clipboard(
+        &self,
+        body: &str,
+        source_device_iden: &str,
+    ) -> Result<(), RequestError> {
+        #[derive(Serialize)]
+        struct SendClip<'a> {
+            #[serde(rename = "type")]
+            kind: &'static str,
+            body: &'a str,
+            source_user_iden: &'a str,
+            source_device_iden: &'a str,
+        }
+
+        let push = serde_json::to_value(SendClip {
+            kind: "clip",
+            body,
+            source_user_iden: self.user_iden().await?,
+            source_device_iden,
+        })
+        .unwrap();
+        self.send_ephemeral(push).await
+    }
+
+    /// Dismisses a notification mirrored from another device.
+    ///
+    /// The notification is identified by the `package_name`,
+    /// `notification_id` and `notification_tag` of a
+    /// [`MirrorNotification`]. The device it was mirrored from, and any
+    /// other devices showing it, will dismiss it.
+    ///
+    /// Dismissals must include the user's identifier, so the first ephemeral
+    /// sent by a client retrieves the current user with
+    /// [`Client::get_user`]. The identifier is then reused by the client and
+    /// its clones.
+    ///
+    /// Example usage:
+    ///
+    /// ```no_run
+    /// use futures_util::StreamExt;
+    ///
+    /// # async fn run() {
+    /// # let client = pb_async::Client::new("...").unwrap();
+    /// let mut events = Box::pin(client.stream());
+    /// while let Some(event) = events.next().await {
+    ///     if let Ok(pb_async::Event::Ephemeral(pb_async::Ephemeral::Mirror(n))) = event {
+    ///         println!("{}: {}", n.title, n.body);
+    ///         let result = client
+    ///             .dismiss_mirrored_notification(
+    ///                 &n.package_name,
+    ///                 &n.notification_id,
+    ///                 n.notification_tag.as_deref(),
+    ///             )
+    ///             .await;
+    ///         if let Err(error) = result {
+    ///             eprintln!("error: {}", error);
+    ///         }
+    ///     }
+    /// }
+    /// # }
+    /// ```
+    pub async fn dismiss_mirrored_notification(
+        &self,
+        package_name: &str,
+        notification_id: &str,
+        notification_tag: Option<&str>,
+    ) -> Result<(), RequestError> {
+        #[derive(Serialize)]
+        struct SendDismissal<'a> {
+            #[serde(rename = "type")]
+            kind: &'static str,
+            package_name: &'a str,
+            notification_id: &'a str,
+            notification_tag: Option<&'a str>,
+            source_user_iden: &'a str,
+        }
+
+        let push = serde_json::to_value(SendDismissal {
+            kind: "dismissal",
+            package_name,
+            notification_id,
+            notification_tag,
+            source_user_iden: self.user_iden().await?,
+        })
+        .unwrap();
+        self.send_ephemeral(push).await
+    }
+
+    /// Sends an ephemeral to all of the user's devices.
+    ///
+    /// If end-to-end encryption is configured, the ephemeral is encrypted.
+    async fn send_ephemeral(&self, push: serde_json::Value) -> Result<(), RequestError> {
+        #[derive(Serialize)]
+        struct SendEphemeral {
+            #[serde(rename = "type")]
+            kind: &'static str,
+            push: serde_json::Value,
+        }
+        let push = match self.encryption {
+            Some(ref encryption) => encryption.encrypt_value(&push),
+            None => push,
+        };
+        let post_data = serde_json::to_string(&SendEphemeral { kind: "push", push }).unwrap();
+
+        self.post("ephemerals", post_data.into(), false).await?;
+        Ok(())
+    }
+}
+
+/// Ephemeral message, received in [`Event::Ephemeral`].
+///
+/// [`Event::Ephemeral`]: crate::Event::Ephemeral
+#[derive(Clone, Debug)]
+pub enum Ephemeral {
+    /// Text copied on another device - see [`Client::send_clipboard`].
+    Clip(Clip),
+    /// Notification mirrored from another device.
+    Mirror(MirrorNotification),
+    /// Mirrored notification dismissed on some device - see
+    /// [`Client::dismiss_mirrored_notification`].
+    Dismissal(Dismissal),
+    /// End-to-end encrypted ephemeral, received when no [`Encryption`] is
+    /// configured. Contains the ciphertext - see [`Encryption::decrypt`].
+    ///
+    /// [`Encryption`]: crate::Encryption
+    /// [`Encryption::decrypt`]: crate::Encryption::decrypt
+    Encrypted(String),
+    /// Ephemeral of a type not otherwise understood by this crate.
+    Other(serde_json::Value),
+}
+
+impl Ephemeral {
+    pub(crate) fn from_value(value: serde_json::Value) -> Result<Self, serde_json::Error> {
+        let kind = value
+            .get("type")
+            .and_then(|kind| kind.as_str())
+            .map(|kind| kind.to_owned());
+        Ok(match kind.as_deref() {
+            Some("clip") => Ephemeral::Clip(serde_json::from_value(value)?),
+            Some("mirror") => Ephemeral::Mirror(serde_json::from_value(value)?),
+            Some("dismissal") => Ephemeral::Dismissal(serde_json::from_value(value)?),
+            _ => Ephemeral::Other(value),
+        })
+    }
+}
+
+/// Text copied to the clipboard on another device.
+#[derive(Clone, Debug, Deserialize)]
+pub struct Clip {
+    /// The copied text.
+    pub body: String,
+    /// User who copied the text.
+    pub source_user_iden: String,
+    /// Device the text was copied on.
+    pub source_device_iden: Option<String>,
+    #[serde(default)]
+    _priv: (),
+}
+
+/// Notification mirrored from another device, usually an Android phone.
+#[derive(Clone, Debug, Deserialize)]
+pub struct MirrorNotification {
+    /// Name of the application which created the notification.
+    pub application_name: Option<String>,
+    /// Package name of the application which created the notification.
+    pub package_name: String,
+    /// Notification title.
+    #[serde(default)]
+    pub title: String,
+    /// Notification body.
+    #[serde(default)]
+    pub body: String,
+    /// Base64-encoded JPEG icon for the notification.
+    pub icon: Option<String>,
+    /// Identifier of the notification within its application.
+    pub notification_id: String,
+    /// Tag of the notification within its application, if any.
+    pub notification_tag: Option<String>,
+    /// Actions which can be taken on the notification.
+    #[serde(default)]
+    pub actions: Vec<MirrorAction>,
+    /// Whether or not the notification can be dismissed - see
+    /// [`Client::dismiss_mirrored_notification`].
+    #[serde(default)]
+    pub dismissible: bool,
+    /// User who owns the device the notification is from.
+    pub source_user_iden: String,
+    /// Device the notification is from.
+    pub source_device_iden: Option<String>,
+    #[serde(default)]
+    _priv: (),
+}
+
+/// Action which can be taken on a [`MirrorNotification`].
+#[derive(Clone, Debug, Deserialize)]
+pub struct MirrorAction {
+    /// Human readable label for the action.
+    pub label: String,
+    /// Key identifying the action to the source device.
+    pub trigger_key: String,
+    #[serde(default)]
+    _priv: (),
+}
+
+/// Dismissal of a mirrored notification.
+#[derive(Clone, Debug, Deserialize)]
+pub struct Dismissal {
+    /// Package name of the application which created the notification.
+    pub package_name: String,
+    /// Identifier of the notification within its application.
+    pub notification_id: String,
+    /// Tag of the notification within its application, if any.
+    pub notification_tag: Option<String>,
+    /// User who dismissed the notification.
+    pub source_user_iden: String,
+    #[serde(default)]
+    _priv: (),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clip() {
+        let clip = Ephemeral::from_value(serde_json::json!({
+            "type": "clip",
+            "body": "copied",
+            "source_user_iden": "user",
+            "source_device_iden": "device",
+        }))
+        .unwrap();
+        match clip {
+            Ephemeral::Clip(clip) => {
+                assert_eq!(clip.body, "copied");
+                assert_eq!(clip.source_device_iden.as_deref(), Some("device"));
+            }
+            other => panic!("expected clip, found {:?}", other),
+        }
+    }
+
+    #[test]
+    fn mirror() {
+        let mirror = Ephemeral::from_value(serde_json::json!({
+            "type": "mirror",
+            "application_name": "Messages",
+            "package_name": "com.example.messages",
+            "title": "New message",
+            "body": "Hello!",
+            "notification_id": "1",
+            "notification_tag": "chat",
+            "actions": [{"label": "Reply", "trigger_key": "reply"}],
+            "dismissible": true,
+            "source_user_iden": "user",
+            "source_device_iden": "phone",
+        }))
+        .unwrap();
+        match mirror {
+            Ephemeral::Mirror(mirror) => {
+                assert_eq!(mirror.package_name, "com.example.messages");
+                assert_eq!(mirror.notification_tag.as_deref(), Some("chat"));
+                assert_eq!(mirror.actions[0].trigger_key, "reply");
+                assert!(mirror.dismissible);
+            }
+            other => panic!("expected mirror, found {:?}", other),
+        }
+    }
+
+    #[test]
+    fn dismissal() {
+        let dismissal = Ephemeral::from_value(serde_json::json!({
+            "type": "dismissal",
+            "package_name": "com.example.messages",
+            "notification_id": "1",
+            "notification_tag": null,
+            "source_user_iden": "user",
+        }))
+        .unwrap();
+        match dismissal {
+            Ephemeral::Dismissal(dismissal) => {
+                assert_eq!(dismissal.notification_id, "1");
+                assert!(dismissal.notification_tag.is_none());
+            }
+            other => panic!("expected dismissal, found {:?}", other),
+        }
+    }
+
+    #[test]
+    fn other() {
+        let value = serde_json::json!({"type": "sms_changed", "notifications": []});
+        match Ephemeral::from_value(value.clone()).unwrap() {
+            Ephemeral::Other(other) => assert_eq!(other, value),
+            other => panic!("expected other ephemeral, found {:?}", other),
+        }
+        // known types with missing fields are errors, not `Other`
+        assert!(Ephemeral::from_value(serde_json::json!({"type": "clip"})).is_err());
+    }
+}
@@ -1,28 +1,47 @@
+use std::fmt;
+
 use http::header::InvalidHeaderValue;
-use {bytes, http, hyper, hyper_tls, serde_json};
+use serde::Deserialize;
+use thiserror::Error;
+use tokio_tungstenite::tungstenite;
 
 /// Error that can occur when creating a client.
-#[derive(Fail, Debug)]
+#[derive(Error, Debug)]
 pub enum StartupError {
     /// Error starting TLS connector
-    #[fail(display = "tls error: {}", _0)]
-    Tls(hyper_tls::Error),
+    #[error("tls error")]
+    Tls(#[source] native_tls::Error),
     /// Token provided was invalid
-    #[fail(display = "invalid token: {} (token: {:?})", _0, _1)]
-    InvalidToken(InvalidHeaderValue, String),
+    #[error("invalid token (token: {1:?})")]
+    InvalidToken(#[source] InvalidHeaderValue, String),
+    /// Base URL or stream URL provided was invalid
+    #[error("invalid url (url: {1:?})")]
+    InvalidUrl(#[source] url::ParseError, String),
+    /// User agent provided was invalid
+    #[error("invalid user agent (user agent: {1:?})")]
+    InvalidUserAgent(#[source] InvalidHeaderValue, String),
+    /// Proxy URL provided was invalid
+    #[error("invalid proxy url: {0}")]
+    InvalidProxy(String),
+    /// Error starting the runtime used by the blocking client
+    #[error("runtime error")]
+    Runtime(#[source] std::io::Error),
 }
 
 /// Error that can occur when running a request.
-#[derive(Fail, Debug)]
+#[derive(Error, Debug)]
 pub enum RequestError {
     /// Error creating request.
-    #[fail(display = "request error: {}", _0)]
-    Http(http::Error),
+    #[error("request error")]
+    Http(#[from] http::Error),
     /// Hyper error.
-    #[fail(display = "hyper error: {}", _0)]
-    Hyper(hyper::Error),
+    #[error("hyper error")]
+    Hyper(#[from] hyper::Error),
+    /// Error connecting to the server or sending the request.
+    #[error("client error")]
+    Client(#[from] hyper_util::client::legacy::Error),
     /// Generic server error.
-    #[fail(display = "server error: {}: {:?}", status, bytes)]
+    #[error("server error: {status}: {bytes:?}")]
     Status {
         /// The failed server status.
         status: http::StatusCode,
@@ -30,31 +49,228 @@ pub enum RequestError {
         bytes: bytes::Bytes,
     },
     /// Invalid JSON in response.
-    #[fail(display = "invalid response json: {}. data: {:?}", error, bytes)]
+    #[error("invalid response json (data: {bytes:?})")]
     Json {
         /// Inner error
+        #[source]
         error: serde_json::Error,
         /// Bytes which could not be decoded
         bytes: bytes::Bytes,
     },
     /// Server error.
-    #[fail(display = "server error: {}: {}", code, message)]
+    #[error("server error: {status}: {code}: {message}")]
     Server {
-        /// Error code string
-        code: String,
+        /// The failed server status.
+        status: http::StatusCode,
+        /// Error code
+        code: ServerErrorCode,
         /// Human readable error message
         message: String,
+        /// ASCII art cat included with errors, if any
+        cat: Option<String>,
+    },
+    /// Server responded `429 Too Many Requests`, as the account's rate
+    /// limit has been used up.
+    ///
+    /// See [`Client::rate_limit`](crate::Client::rate_limit).
+    #[error("rate limit exceeded")]
+    RateLimited {
+        /// Time at which the rate limit is reset, in seconds since the unix
+        /// epoch, if the server sent it.
+        reset_at: Option<f64>,
+    },
+    /// Error decrypting response.
+    #[error("encryption error")]
+    Encryption(#[source] EncryptionError),
+    /// Request took longer than the timeout configured with
+    /// [`ClientBuilder::timeout`].
+    ///
+    /// [`ClientBuilder::timeout`]: crate::ClientBuilder::timeout
+    #[error("request timed out")]
+    Timeout,
+}
+
+/// Error that can occur in the realtime event stream.
+#[derive(Error, Debug)]
+pub enum StreamError {
+    /// Token could not be used in the stream URL.
+    #[error("invalid token for stream url")]
+    InvalidToken,
+    /// Stream URL was invalid.
+    #[error("invalid stream url")]
+    Url(#[source] url::ParseError),
+    /// Websocket error.
+    #[error("websocket error")]
+    WebSocket(#[source] tungstenite::Error),
+    /// No message received from the server within the heartbeat interval.
+    #[error("missed heartbeat from server")]
+    MissedHeartbeat,
+    /// Connection was not established within the heartbeat interval.
+    #[error("timed out connecting to stream")]
+    ConnectTimeout,
+    /// Invalid JSON in message.
+    #[error("invalid message json (data: {text:?})")]
+    Json {
+        /// Inner error
+        #[source]
+        error: serde_json::Error,
+        /// Message which could not be decoded
+        text: String,
     },
+    /// Error decrypting message.
+    #[error("encryption error")]
+    Encryption(#[source] EncryptionError),
+}
+
+/// Error that can occur when decrypting end-to-end encrypted data.
+#[derive(Error, Debug)]
+pub enum EncryptionError {
+    /// Data was encrypted, but no encryption key was configured.
+    #[error("received encrypted data, but no encryption key is configured")]
+    NoKey,
+    /// Ciphertext was not valid base64.
+    #[error("invalid base64 in ciphertext")]
+    Base64(#[source] base64::DecodeError),
+    /// Ciphertext was too short to contain the encryption header.
+    #[error("ciphertext too short")]
+    TooShort,
+    /// Ciphertext used an unknown encryption version.
+    #[error("unsupported encryption version: {0}")]
+    UnsupportedVersion(u8),
+    /// Decryption failed, usually due to an incorrect password.
+    #[error("decryption failed (is the password correct?)")]
+    Decryption,
+    /// Decrypted message was not valid JSON.
+    #[error("invalid decrypted json")]
+    Json(#[source] serde_json::Error),
 }
 
-impl From<hyper::Error> for RequestError {
-    fn from(e: hyper::Error) -> Self {
-        RequestError::Hyper(e)
+impl RequestError {
+    /// Retrieves the error code sent by the server, if this is a
+    /// [`RequestError::Server`].
+    pub fn server_code(&self) -> Option<&ServerErrorCode> {
+        match *self {
+            RequestError::Server { ref code, .. } => Some(code),
+            _ => None,
+        }
     }
 }
 
-impl From<http::Error> for RequestError {
-    fn from(e: http::Error) -> Self {
-        RequestError::Http(e)
+/// Error code sent by the server, included in [`RequestError::Server`].
+///
+/// Example usage:
+///
+/// ```no_run
+/// use pb_async::{RequestError, ServerErrorCode};
+///
+/// # async fn run() {
+/// # let client = pb_async::Client::new("...").unwrap();
+/// match client.get_user().await {
+///     Ok(user) => println!("logged in as {}", user.email),
+///     Err(RequestError::Server {
+///         code: ServerErrorCode::InvalidAccessToken,
+///         ..
+///     }) => eprintln!("access token has expired or been revoked"),
+///     Err(error) => eprintln!("error: {}", error),
+/// }
+/// # }
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Deserialize)]
+#[serde(from = "String")]
+pub enum ServerErrorCode {
+    /// Access token is missing, invalid or has been revoked.
+    InvalidAccessToken,
+    /// Request was malformed or had invalid parameters.
+    InvalidRequest,
+    /// Requested object does not exist.
+    NotFound,
+    /// Feature requires a PushBullet Pro account.
+    PushbulletProRequired,
+    /// Too many requests were made, and the account is rate limited.
+    Ratelimited,
+    /// Uploaded file is larger than the user's maximum upload size.
+    FileTooLarge,
+    /// Any other error code.
+    Other(String),
+}
+
+impl ServerErrorCode {
+    /// Retrieves the error code as sent by the server, such as
+    /// `"invalid_access_token"`.
+    pub fn as_str(&self) -> &str {
+        match *self {
+            ServerErrorCode::InvalidAccessToken => "invalid_access_token",
+            ServerErrorCode::InvalidRequest => "invalid_request",
+            ServerErrorCode::NotFound => "not_found",
+            ServerErrorCode::PushbulletProRequired => "pushbullet_pro_required",
+            ServerErrorCode::Ratelimited => "ratelimited",
+            ServerErrorCode::FileTooLarge => "file_too_large",
+            ServerErrorCode::Other(ref code) => code,
+        }
+    }
+}
+
+impl From<String> for ServerErrorCode {
+    fn from(code: String) -> Self {
+        match &*code {
+            "invalid_access_token" => ServerErrorCode::InvalidAccessToken,
+            "invalid_request" => ServerErrorCode::InvalidRequest,
+            "not_found" => ServerErrorCode::NotFound,
+            "pushbullet_pro_required" => ServerErrorCode::PushbulletProRequired,
+            "ratelimited" => ServerErrorCode::Ratelimited,
+            "file_too_large" => ServerErrorCode::FileTooLarge,
+            _ => ServerErrorCode::Other(code),
+        }
+    }
+}
+
+impl fmt::Display for ServerErrorCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Displays an error along with the chain of errors which caused it, for
+/// logging.
+pub(crate) struct Chain<'a>(pub &'a (dyn std::error::Error + 'static));
+
+impl fmt::Display for Chain<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)?;
+        let mut source = self.0.source();
+        while let Some(error) = source {
+            write!(f, ": {}", error)?;
+            source = error.source();
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::error::Error;
+
+    use super::*;
+
+    #[test]
+    fn sources() {
+        let http_error = http::Request::builder().uri("\0").body(()).unwrap_err();
+        let error = RequestError::from(http_error);
+        assert_eq!(error.to_string(), "request error");
+        assert!(error.source().unwrap().is::<http::Error>());
+
+        let json_error = serde_json::from_slice::<()>(b"{").unwrap_err();
+        let error = RequestError::Json {
+            error: json_error,
+            bytes: bytes::Bytes::from_static(b"{"),
+        };
+        assert!(error.source().unwrap().is::<serde_json::Error>());
+
+        let error = StreamError::Encryption(EncryptionError::TooShort);
+        assert_eq!(error.source().unwrap().to_string(), "ciphertext too short");
+        assert_eq!(
+            Chain(&error).to_string(),
+            "encryption error: ciphertext too short"
+        );
     }
 }
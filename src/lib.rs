@@ -1,4 +1,8 @@
 #![warn(missing_docs)]
+// Public structs keep a private `_priv` field so fields can be added without
+// breaking changes, and event enums are left unboxed so they can be matched
+// directly.
+#![allow(clippy::manual_non_exhaustive, clippy::large_enum_variant)]
 #![doc(html_root_url = "https://docs.rs/pb-async/0.1.0/")]
 //! Asynchronous PushBullet client for Rust.
 //!
@@ -10,34 +14,28 @@
 //! Then you can use any of the request methods on [`Client`] to perform API
 //! requests.
 //!
-//! All requests are futures, so you'll need to run them in some kind of
-//! futures execution context. I recommend using the [`tokio`] crate.
+//! All requests are `async`, and must be run within a [`tokio`] runtime.
 //!
 //! # Example
 //!
 //! ```no_run
-//! extern crate futures;
-//! extern crate pb_async;
-//! extern crate tokio;
+//! #[tokio::main]
+//! async fn main() {
+//!     let client = pb_async::Client::new("...").unwrap();
 //!
-//! use futures::Future;
-//!
-//! # fn main() {
-//! let client = pb_async::Client::new("...").unwrap();
-//!
-//! tokio::run(
-//!     client.push(
-//!         pb_async::PushTarget::SelfUser {},
-//!         pb_async::PushData::Note {
-//!             title: "",
-//!             body: "Hello, user!",
-//!         },
-//!     ).or_else(|error| {
+//!     let result = client
+//!         .push(
+//!             pb_async::PushTarget::SelfUser {},
+//!             pb_async::PushData::Note {
+//!                 title: "",
+//!                 body: "Hello, user!",
+//!             },
+//!         )
+//!         .await;
+//!     if let Err(error) = result {
 //!         eprintln!("error: {}", error);
-//!         Ok(())
-//!     })
-//! );
-//! # }
+//!     }
+//! }
 //! ```
 //!
 //! See [`Client`] for more snippets.
@@ -47,38 +45,73 @@
 //! [`tokio`]: https://crates.io/crates/tokio
 //! [full example programs]: https://github.com/daboross/pb-async/tree/master/examples/
 //! [the PushBullet account settings]: https://www.pushbullet.com/#settings/account
-extern crate bytes;
-extern crate failure;
-extern crate futures;
-extern crate http;
-extern crate hyper;
-extern crate hyper_tls;
-extern crate mpart_async;
-extern crate serde;
-extern crate serde_json;
 #[macro_use]
 extern crate log;
-#[macro_use]
-extern crate failure_derive;
-#[macro_use]
-extern crate serde_derive;
 
+#[cfg(feature = "blocking")]
+pub mod blocking;
+mod body;
+mod builder;
+mod encryption;
+mod ephemeral;
 mod errors;
+mod oauth;
+mod permanents;
+mod ratelimit;
+mod retry;
+mod stream;
+mod sync;
+#[cfg(feature = "test-server")]
+pub mod testing;
+
+pub use crate::body::RequestBody;
+pub use crate::builder::ClientBuilder;
+pub use crate::encryption::Encryption;
+pub use crate::ephemeral::{Clip, Dismissal, Ephemeral, MirrorAction, MirrorNotification};
+pub use crate::errors::{
+    EncryptionError, RequestError, ServerErrorCode, StartupError, StreamError,
+};
+pub use crate::oauth::{AccessToken, Grant, OAuthClient, OAuthClientInfo, ResponseType};
+pub use crate::permanents::{SmsDirection, SmsMessage, SmsMessageType, SmsRecipient, SmsThread};
+pub use crate::ratelimit::RateLimit;
+pub use crate::retry::RetryPolicy;
+pub use crate::stream::{Event, Tickle};
+pub use crate::sync::{SyncEvent, SyncStream};
+
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::Duration;
 
-pub use errors::{RequestError, StartupError};
+use bytes::Bytes;
+use futures_util::stream::{Stream, TryStreamExt};
+use http::header::{HeaderMap, HeaderValue};
+use http_body_util::BodyExt;
+use hyper_util::client::legacy::connect::HttpConnector;
+use ring::rand::SecureRandom;
+use serde::{Deserialize, Serialize};
 
-use futures::{Future, Stream};
-use http::header::HeaderValue;
+use crate::errors::Chain;
 
 static API_ROOT: &str = "https://api.pushbullet.com/v2/";
 static TOKEN_HEADER: &str = "Access-Token";
 
-type HyperClient = hyper::Client<hyper_tls::HttpsConnector<hyper::client::HttpConnector>>;
+type HyperClient =
+    hyper_util::client::legacy::Client<hyper_tls::HttpsConnector<HttpConnector>, RequestBody>;
+type ProxiedClient = hyper_util::client::legacy::Client<
+    hyper_http_proxy::ProxyConnector<HttpConnector>,
+    RequestBody,
+>;
 
 /// PushBullet client
+#[derive(Clone)]
 pub struct Client {
     token: HeaderValue,
-    client: HyperClient,
+    transport: Transport,
+    encryption: Option<Arc<Encryption>>,
+    /// Identifier of the logged in user, once retrieved. Shared between
+    /// clones.
+    user_iden: Arc<OnceLock<String>>,
+    api_root: String,
+    stream_root: String,
 }
 
 impl Client {
@@ -92,22 +125,41 @@ impl Client {
     ///     .expect("expected client creation to succeed");
     /// ```
     pub fn new(token: &str) -> Result<Self, StartupError> {
-        let mut connector = hyper_tls::HttpsConnector::new(1).map_err(StartupError::Tls)?;
-        connector.force_https(true);
-        Ok(Client {
-            token: HeaderValue::from_str(token)
-                .map_err(|e| StartupError::InvalidToken(e, token.to_owned()))?,
-            client: hyper::Client::builder().keep_alive(true).build(connector),
-        })
+        Client::builder(token).build()
     }
 
     /// Create a new client with a given token and an existing hyper client.
     pub fn with_client(token: &str, client: HyperClient) -> Result<Self, StartupError> {
-        Ok(Client {
-            token: HeaderValue::from_str(token)
-                .map_err(|e| StartupError::InvalidToken(e, token.to_owned()))?,
-            client: client,
-        })
+        Client::builder(token).hyper_client(client).build()
+    }
+
+    /// Sets the key used for end-to-end encryption, or disables end-to-end
+    /// encryption if `None`.
+    ///
+    /// See [`Encryption`] for more information.
+    pub fn set_encryption(&mut self, encryption: Option<Encryption>) {
+        self.encryption = encryption.map(Arc::new);
+    }
+
+    /// Retrieves the rate limit state reported by the server in response to
+    /// the latest request, or `None` if no request has completed yet.
+    ///
+    /// Clones of a client share the same state.
+    ///
+    /// Example usage:
+    ///
+    /// ```no_run
+    /// # async fn run() -> Result<(), pb_async::RequestError> {
+    /// # let client = pb_async::Client::new("...").unwrap();
+    /// client.get_user().await?;
+    /// if let Some(rate_limit) = client.rate_limit() {
+    ///     println!("{} of {} quota units remaining", rate_limit.remaining, rate_limit.limit);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn rate_limit(&self) -> Option<RateLimit> {
+        self.transport.rate_limit()
     }
 
     /// Retrieves information of the logged in user.
@@ -115,299 +167,1240 @@ impl Client {
     /// Example usage:
     ///
     /// ```no_run
-    /// extern crate futures;
-    /// extern crate pb_async;
-    /// extern crate tokio;
+    /// # async fn run() -> Result<(), pb_async::RequestError> {
+    /// # let client = pb_async::Client::new("...").unwrap();
+    /// let user_info = client.get_user().await?;
+    /// println!("User email is {}", user_info.email);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn get_user(&self) -> Result<User, RequestError> {
+        let (bytes, data) = self.get("users/me").await?;
+        let user: User =
+            serde_json::from_value(data).map_err(|error| RequestError::Json { error, bytes })?;
+        let _ = self.user_iden.set(user.iden.clone());
+        Ok(user)
+    }
+
+    /// Retrieves the identifier of the logged in user, only making a request
+    /// the first time.
+    async fn user_iden(&self) -> Result<&str, RequestError> {
+        if self.user_iden.get().is_none() {
+            self.get_user().await?;
+        }
+        Ok(self.user_iden.get().expect("expected user iden to be set"))
+    }
+
+    /// Retrieves a list of devices.
+    ///
+    /// Example usage:
+    ///
+    /// ```no_run
+    /// # async fn run() -> Result<(), pb_async::RequestError> {
+    /// # let client = pb_async::Client::new("...").unwrap();
+    /// let devices = client.list_devices().await?;
+    /// println!("Devices: {:#?}", devices);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn list_devices(&self) -> Result<Vec<Device>, RequestError> {
+        #[derive(Deserialize)]
+        struct Devices {
+            devices: Vec<Device>,
+        }
+        let (bytes, data) = self.get("devices").await?;
+        let d: Devices =
+            serde_json::from_value(data).map_err(|error| RequestError::Json { error, bytes })?;
+        Ok(d.devices)
+    }
+
+    /// Creates a new device.
+    ///
+    /// Returns the device as created by the server, including its
+    /// identifier.
+    ///
+    /// Example usage:
+    ///
+    /// ```no_run
+    /// # async fn run() -> Result<(), pb_async::RequestError> {
+    /// # let client = pb_async::Client::new("...").unwrap();
+    /// let device = pb_async::DeviceParams {
+    ///     nickname: Some("build-server"),
+    ///     model: Some("pb-async"),
+    ///     ..Default::default()
+    /// };
+    ///
+    /// let device = client.create_device(&device).await?;
+    /// println!("Created device {}", device.iden);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn create_device(&self, params: &DeviceParams<'_>) -> Result<Device, RequestError> {
+        let post_data = serde_json::to_string(params).unwrap();
+
+        let (bytes, data) = self.post("devices", post_data.into(), false).await?;
+        serde_json::from_value(data).map_err(|error| RequestError::Json { error, bytes })
+    }
+
+    /// Updates an existing device.
+    ///
+    /// Only fields set in `params` are changed. Returns the device as
+    /// updated by the server.
+    ///
+    /// Example usage:
+    ///
+    /// ```no_run
+    /// # async fn run() -> Result<(), pb_async::RequestError> {
+    /// # let client = pb_async::Client::new("...").unwrap();
+    /// let changes = pb_async::DeviceParams {
+    ///     nickname: Some("old-build-server"),
+    ///     ..Default::default()
+    /// };
+    ///
+    /// let device = client.update_device("ujpah72o0sjAoRtnM0jc", &changes).await?;
+    /// println!("Updated device: {:#?}", device);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn update_device(
+        &self,
+        iden: &str,
+        params: &DeviceParams<'_>,
+    ) -> Result<Device, RequestError> {
+        let post_data = serde_json::to_string(params).unwrap();
+
+        let (bytes, data) = self
+            .post(&format!("devices/{}", iden), post_data.into(), false)
+            .await?;
+        serde_json::from_value(data).map_err(|error| RequestError::Json { error, bytes })
+    }
+
+    /// Deletes a device.
     ///
-    /// use futures::Future;
+    /// Example usage:
     ///
-    /// # fn main() {
+    /// ```no_run
+    /// # async fn run() -> Result<(), pb_async::RequestError> {
     /// # let client = pb_async::Client::new("...").unwrap();
+    /// client.delete_device("ujpah72o0sjAoRtnM0jc").await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn delete_device(&self, iden: &str) -> Result<(), RequestError> {
+        self.delete(&format!("devices/{}", iden)).await?;
+        Ok(())
+    }
+
+    /// Pushes some data to a target.
+    ///
+    /// Returns the push as created by the server, including its identifier.
+    ///
+    /// If a [`RetryPolicy`] is configured, the push is sent with a generated
+    /// guid, so that it is safe to retry.
     ///
-    /// tokio::executor::spawn(client.get_user().and_then(|user_info| {
-    ///     println!("User email is {}", user_info.email);
-    ///     Ok(())
-    /// }).or_else(|error| {
-    ///     eprintln!("error: {}", error);
-    ///     Ok(())
-    /// }));
+    /// Example usage:
+    ///
+    /// ```no_run
+    /// # async fn run() -> Result<(), pb_async::RequestError> {
+    /// # let client = pb_async::Client::new("...").unwrap();
+    /// let push = client
+    ///     .push(
+    ///         pb_async::PushTarget::SelfUser {},
+    ///         pb_async::PushData::Note {
+    ///             title: "",
+    ///             body: "Hello, user!",
+    ///         },
+    ///     )
+    ///     .await?;
+    /// println!("Created push {}", push.iden);
+    /// # Ok(())
     /// # }
     /// ```
-    pub fn get_user(&self) -> impl Future<Item = User, Error = RequestError> {
-        self.get("users/me").and_then(|(bytes, data)| {
-            serde_json::from_value(data).map_err(|error| RequestError::Json { error, bytes })
+    pub async fn push(
+        &self,
+        target: PushTarget<'_>,
+        data: PushData<'_>,
+    ) -> Result<Push, RequestError> {
+        #[derive(Serialize)]
+        struct Push<'a> {
+            #[serde(flatten)]
+            data: PushData<'a>,
+            #[serde(flatten)]
+            target: PushTarget<'a>,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            guid: Option<String>,
+        }
+
+        // the server ignores pushes with a guid it has already seen
+        let retry = self.transport.retry.is_enabled();
+        let guid = if retry { Some(random_hex()) } else { None };
+        let post_data = serde_json::to_string(&Push { target, data, guid }).unwrap();
+
+        let (bytes, data) = self.post("pushes", post_data.into(), retry).await?;
+        serde_json::from_value(data).map_err(|error| RequestError::Json { error, bytes })
+    }
+
+    /// Uploads a file prior to pushing it via [`Client::push`].
+    ///
+    /// `upload_data` is the file contents, such as a `Vec<u8>` or `&'static
+    /// str`. To upload a large file without keeping it all in memory, use
+    /// [`Client::upload_request_stream`].
+    ///
+    /// Example usage:
+    ///
+    /// ```no_run
+    /// # async fn run() -> Result<(), pb_async::RequestError> {
+    /// # let client = pb_async::Client::new("...").unwrap();
+    /// let file_data = client
+    ///     .upload_request("hello.txt", "text/plain", "Hello, world!\n")
+    ///     .await?;
+    /// client
+    ///     .push(
+    ///         pb_async::PushTarget::SelfUser {},
+    ///         pb_async::PushData::File {
+    ///             body: "",
+    ///             file_name: &file_data.file_name,
+    ///             file_type: &file_data.file_type,
+    ///             file_url: &file_data.file_url,
+    ///         },
+    ///     )
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn upload_request(
+        &self,
+        file_name: &str,
+        file_type: &str,
+        upload_data: impl Into<Bytes>,
+    ) -> Result<UploadRequestResponse, RequestError> {
+        let upload_data = upload_data.into();
+        self.upload(file_name, file_type, move |head, tail| {
+            let mut body = Vec::with_capacity(head.len() + upload_data.len() + tail.len());
+            body.extend_from_slice(&head);
+            body.extend_from_slice(&upload_data);
+            body.extend_from_slice(&tail);
+            body.into()
         })
+        .await
     }
 
-    /// Retrieves a list of devices.
+    /// Uploads a file prior to pushing it via [`Client::push`], streaming
+    /// its contents.
+    ///
+    /// `upload_data` is sent as it produces data, so the file never needs to
+    /// be kept in memory in full. A file can be streamed with, for example,
+    /// `tokio_util::io::ReaderStream`. If the stream produces an error, the
+    /// upload is aborted and fails with [`RequestError::Client`].
+    ///
+    /// Unlike other requests, streamed uploads are never retried.
     ///
     /// Example usage:
     ///
     /// ```no_run
-    /// extern crate futures;
-    /// extern crate pb_async;
-    /// extern crate tokio;
+    /// # async fn run() -> Result<(), pb_async::RequestError> {
+    /// # let client = pb_async::Client::new("...").unwrap();
+    /// let chunks = vec!["Hello, ", "world!\n"]
+    ///     .into_iter()
+    ///     .map(|chunk| Ok::<_, std::io::Error>(bytes::Bytes::from(chunk)));
     ///
-    /// use futures::Future;
+    /// let file_data = client
+    ///     .upload_request_stream("hello.txt", "text/plain", futures_util::stream::iter(chunks))
+    ///     .await?;
+    /// println!("Uploaded to {}", file_data.file_url);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn upload_request_stream<S, E>(
+        &self,
+        file_name: &str,
+        file_type: &str,
+        upload_data: S,
+    ) -> Result<UploadRequestResponse, RequestError>
+    where
+        S: Stream<Item = Result<Bytes, E>> + Send + 'static,
+        E: Into<Box<dyn std::error::Error + Send + Sync>> + 'static,
+    {
+        use futures_util::stream::{self, StreamExt};
+        self.upload(file_name, file_type, move |head, tail| {
+            let framing = |part| stream::once(async move { Ok(part) });
+            RequestBody::stream(framing(head).chain(upload_data).chain(framing(tail)))
+        })
+        .await
+    }
+
+    /// Requests an upload URL, and uploads the body created by `body` from
+    /// the multipart framing to send before and after the file contents.
+    async fn upload(
+        &self,
+        file_name: &str,
+        file_type: &str,
+        body: impl FnOnce(Bytes, Bytes) -> RequestBody,
+    ) -> Result<UploadRequestResponse, RequestError> {
+        #[derive(Serialize)]
+        struct Upload<'a> {
+            file_name: &'a str,
+            file_type: &'a str,
+        }
+        let post_data = serde_json::to_string(&Upload {
+            file_name,
+            file_type,
+        })
+        .unwrap();
+        let (bytes, data) = self.post("upload-request", post_data.into(), false).await?;
+        let RawUploadRequestResponse {
+            file_name,
+            file_type,
+            file_url,
+            upload_url,
+        } = serde_json::from_value(data).map_err(|error| RequestError::Json { error, bytes })?;
+
+        let (boundary, head, tail) = multipart_framing(&file_name, &file_type);
+        let request = http::Request::post(upload_url)
+            .header(TOKEN_HEADER, self.token.clone())
+            .header(
+                http::header::CONTENT_TYPE,
+                format!("multipart/form-data; boundary={}", boundary),
+            )
+            .body(body(head, tail))?;
+
+        let (parts, bytes) = self.transport.request(request).await?;
+        if !parts.status.is_success() {
+            return Err(RequestError::Status {
+                status: parts.status,
+                bytes,
+            });
+        }
+        Ok(UploadRequestResponse {
+            file_name,
+            file_type,
+            file_url,
+            _priv: (),
+        })
+    }
+
+    /// Retrieves a single page of push history.
+    ///
+    /// The returned [`PushPage`] contains a cursor if more pushes are
+    /// available. To retrieve the next page, call this again with
+    /// [`ListPushes.cursor`] set to that cursor, or use
+    /// [`Client::all_pushes`] to follow cursors automatically.
     ///
-    /// # fn main() {
+    /// Example usage:
+    ///
+    /// ```no_run
+    /// # async fn run() -> Result<(), pb_async::RequestError> {
     /// # let client = pb_async::Client::new("...").unwrap();
+    /// let params = pb_async::ListPushes {
+    ///     active: Some(true),
+    ///     limit: Some(10),
+    ///     ..Default::default()
+    /// };
     ///
-    /// tokio::executor::spawn(client.list_devices().and_then(|devices| {
-    ///     println!("Devices: {:#?}", devices);
-    ///     Ok(())
-    /// }).or_else(|error| {
-    ///     eprintln!("error: {}", error);
-    ///     Ok(())
-    /// }));
+    /// let page = client.list_pushes(&params).await?;
+    /// println!("Recent pushes: {:#?}", page.pushes);
+    /// # Ok(())
     /// # }
     /// ```
-    pub fn list_devices(&self) -> impl Future<Item = Vec<Device>, Error = RequestError> {
+    ///
+    /// [`ListPushes.cursor`]: ListPushes::cursor
+    pub async fn list_pushes(&self, params: &ListPushes) -> Result<PushPage, RequestError> {
+        // the serializer isn't `Send`, so it must not be held across an await
+        let query = {
+            let mut query = url::form_urlencoded::Serializer::new(String::new());
+            if let Some(active) = params.active {
+                query.append_pair("active", if active { "true" } else { "false" });
+            }
+            if let Some(modified_after) = params.modified_after {
+                query.append_pair("modified_after", &modified_after.to_string());
+            }
+            if let Some(limit) = params.limit {
+                query.append_pair("limit", &limit.to_string());
+            }
+            if let Some(ref cursor) = params.cursor {
+                query.append_pair("cursor", cursor);
+            }
+            query.finish()
+        };
+        let (bytes, data) = self.get_with_query("pushes", query).await?;
+        serde_json::from_value(data).map_err(|error| RequestError::Json { error, bytes })
+    }
+
+    /// Retrieves all push history matching the given parameters.
+    ///
+    /// Pages are requested lazily as the stream is polled, following the
+    /// server's cursor until no more pushes are available. If
+    /// [`ListPushes.limit`] is set, it applies to each page rather than the
+    /// stream as a whole.
+    ///
+    /// Example usage:
+    ///
+    /// ```no_run
+    /// use futures_util::TryStreamExt;
+    ///
+    /// # async fn run() -> Result<(), pb_async::RequestError> {
+    /// # let client = pb_async::Client::new("...").unwrap();
+    /// let params = pb_async::ListPushes {
+    ///     active: Some(true),
+    ///     ..Default::default()
+    /// };
+    ///
+    /// let mut pushes = Box::pin(client.all_pushes(params));
+    /// while let Some(push) = pushes.try_next().await? {
+    ///     println!("Push: {:#?}", push);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [`ListPushes.limit`]: ListPushes::limit
+    pub fn all_pushes(
+        &self,
+        params: ListPushes,
+    ) -> impl Stream<Item = Result<Push, RequestError>> + Send + 'static {
+        use futures_util::stream;
+
+        let client = self.clone();
+        stream::try_unfold(Some(params), move |params| {
+            let client = client.clone();
+            async move {
+                let params = match params {
+                    Some(params) => params,
+                    None => return Ok(None),
+                };
+                let page = client.list_pushes(&params).await?;
+                let next = page.cursor.map(|cursor| ListPushes {
+                    cursor: Some(cursor),
+                    ..params
+                });
+                let pushes = stream::iter(page.pushes.into_iter().map(Ok));
+                Ok::<_, RequestError>(Some((pushes, next)))
+            }
+        })
+        .try_flatten()
+    }
+
+    /// Updates a push.
+    ///
+    /// Returns the push as updated by the server.
+    ///
+    /// Example usage:
+    ///
+    /// ```no_run
+    /// # async fn run() -> Result<(), pb_async::RequestError> {
+    /// # let client = pb_async::Client::new("...").unwrap();
+    /// let update = pb_async::PushUpdate {
+    ///     dismissed: Some(true),
+    ///     ..Default::default()
+    /// };
+    ///
+    /// let push = client.update_push("ujpah72o0sjAoRtnM0jc", &update).await?;
+    /// println!("Updated push: {:#?}", push);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn update_push(&self, iden: &str, update: &PushUpdate) -> Result<Push, RequestError> {
+        let post_data = serde_json::to_string(update).unwrap();
+
+        let (bytes, data) = self
+            .post(&format!("pushes/{}", iden), post_data.into(), false)
+            .await?;
+        serde_json::from_value(data).map_err(|error| RequestError::Json { error, bytes })
+    }
+
+    /// Marks a push as dismissed.
+    ///
+    /// This is shorthand for [`Client::update_push`] with
+    /// [`PushUpdate.dismissed`] set.
+    ///
+    /// Example usage:
+    ///
+    /// ```no_run
+    /// # async fn run() -> Result<(), pb_async::RequestError> {
+    /// # let client = pb_async::Client::new("...").unwrap();
+    /// client.dismiss_push("ujpah72o0sjAoRtnM0jc").await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [`PushUpdate.dismissed`]: PushUpdate::dismissed
+    pub async fn dismiss_push(&self, iden: &str) -> Result<Push, RequestError> {
+        self.update_push(
+            iden,
+            &PushUpdate {
+                dismissed: Some(true),
+            },
+        )
+        .await
+    }
+
+    /// Deletes a push.
+    ///
+    /// Example usage:
+    ///
+    /// ```no_run
+    /// # async fn run() -> Result<(), pb_async::RequestError> {
+    /// # let client = pb_async::Client::new("...").unwrap();
+    /// client.delete_push("ujpah72o0sjAoRtnM0jc").await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn delete_push(&self, iden: &str) -> Result<(), RequestError> {
+        self.delete(&format!("pushes/{}", iden)).await?;
+        Ok(())
+    }
+
+    /// Deletes all of the user's pushes.
+    ///
+    /// The server performs this asynchronously, so pushes may still show up
+    /// in [`Client::list_pushes`] for a short while after this completes.
+    ///
+    /// Example usage:
+    ///
+    /// ```no_run
+    /// # async fn run() -> Result<(), pb_async::RequestError> {
+    /// # let client = pb_async::Client::new("...").unwrap();
+    /// client.delete_all_pushes().await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn delete_all_pushes(&self) -> Result<(), RequestError> {
+        self.delete("pushes").await?;
+        Ok(())
+    }
+
+    /// Retrieves a list of chats.
+    ///
+    /// Chats are contacts the user has added in PushBullet.
+    ///
+    /// Example usage:
+    ///
+    /// ```no_run
+    /// # async fn run() -> Result<(), pb_async::RequestError> {
+    /// # let client = pb_async::Client::new("...").unwrap();
+    /// let chats = client.list_chats().await?;
+    /// println!("Chats: {:#?}", chats);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn list_chats(&self) -> Result<Vec<Chat>, RequestError> {
         #[derive(Deserialize)]
-        struct Devices {
-            devices: Vec<Device>,
+        struct Chats {
+            chats: Vec<Chat>,
         }
-        self.get("devices").and_then(|(bytes, data)| {
-            let d: Devices = serde_json::from_value(data).map_err(|error| RequestError::Json {
-                error,
-                bytes: bytes.clone(),
-            })?;
-            Ok(d.devices)
-        })
+        let (bytes, data) = self.get("chats").await?;
+        let c: Chats =
+            serde_json::from_value(data).map_err(|error| RequestError::Json { error, bytes })?;
+        Ok(c.chats)
+    }
+
+    /// Creates a chat with a given email address.
+    ///
+    /// Once created, pushes sent with [`PushTarget::User`] to this email
+    /// will show up in the chat.
+    ///
+    /// Example usage:
+    ///
+    /// ```no_run
+    /// # async fn run() -> Result<(), pb_async::RequestError> {
+    /// # let client = pb_async::Client::new("...").unwrap();
+    /// let chat = client.create_chat("carmack@idsoftware.com").await?;
+    /// println!("Created chat {}", chat.iden);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn create_chat(&self, email: &str) -> Result<Chat, RequestError> {
+        #[derive(Serialize)]
+        struct CreateChat<'a> {
+            email: &'a str,
+        }
+        let post_data = serde_json::to_string(&CreateChat { email }).unwrap();
+
+        let (bytes, data) = self.post("chats", post_data.into(), false).await?;
+        serde_json::from_value(data).map_err(|error| RequestError::Json { error, bytes })
+    }
+
+    /// Mutes or unmutes a chat.
+    ///
+    /// Pushes from muted chats don't show notifications.
+    ///
+    /// Example usage:
+    ///
+    /// ```no_run
+    /// # async fn run() -> Result<(), pb_async::RequestError> {
+    /// # let client = pb_async::Client::new("...").unwrap();
+    /// client.update_chat("ujlxm0aiT4Osjz7Rz6Ou1K", true).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn update_chat(&self, iden: &str, muted: bool) -> Result<Chat, RequestError> {
+        #[derive(Serialize)]
+        struct UpdateChat {
+            muted: bool,
+        }
+        let post_data = serde_json::to_string(&UpdateChat { muted }).unwrap();
+
+        let (bytes, data) = self
+            .post(&format!("chats/{}", iden), post_data.into(), false)
+            .await?;
+        serde_json::from_value(data).map_err(|error| RequestError::Json { error, bytes })
+    }
+
+    /// Deletes a chat.
+    ///
+    /// Example usage:
+    ///
+    /// ```no_run
+    /// # async fn run() -> Result<(), pb_async::RequestError> {
+    /// # let client = pb_async::Client::new("...").unwrap();
+    /// client.delete_chat("ujlxm0aiT4Osjz7Rz6Ou1K").await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn delete_chat(&self, iden: &str) -> Result<(), RequestError> {
+        self.delete(&format!("chats/{}", iden)).await?;
+        Ok(())
+    }
+
+    /// Retrieves a list of channel subscriptions.
+    ///
+    /// Example usage:
+    ///
+    /// ```no_run
+    /// # async fn run() -> Result<(), pb_async::RequestError> {
+    /// # let client = pb_async::Client::new("...").unwrap();
+    /// for subscription in client.list_subscriptions().await? {
+    ///     println!("Subscribed to {}", subscription.channel.tag);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn list_subscriptions(&self) -> Result<Vec<Subscription>, RequestError> {
+        #[derive(Deserialize)]
+        struct Subscriptions {
+            subscriptions: Vec<Subscription>,
+        }
+        let (bytes, data) = self.get("subscriptions").await?;
+        let s: Subscriptions =
+            serde_json::from_value(data).map_err(|error| RequestError::Json { error, bytes })?;
+        Ok(s.subscriptions)
+    }
+
+    /// Subscribes to a channel by tag.
+    ///
+    /// Example usage:
+    ///
+    /// ```no_run
+    /// # async fn run() -> Result<(), pb_async::RequestError> {
+    /// # let client = pb_async::Client::new("...").unwrap();
+    /// let subscription = client.create_subscription("jblow").await?;
+    /// println!("Created subscription {}", subscription.iden);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn create_subscription(
+        &self,
+        channel_tag: &str,
+    ) -> Result<Subscription, RequestError> {
+        #[derive(Serialize)]
+        struct CreateSubscription<'a> {
+            channel_tag: &'a str,
+        }
+        let post_data = serde_json::to_string(&CreateSubscription { channel_tag }).unwrap();
+
+        let (bytes, data) = self.post("subscriptions", post_data.into(), false).await?;
+        serde_json::from_value(data).map_err(|error| RequestError::Json { error, bytes })
+    }
+
+    /// Mutes or unmutes a channel subscription.
+    ///
+    /// Pushes from muted channels don't show notifications.
+    ///
+    /// Example usage:
+    ///
+    /// ```no_run
+    /// # async fn run() -> Result<(), pb_async::RequestError> {
+    /// # let client = pb_async::Client::new("...").unwrap();
+    /// client.update_subscription("ujpah72o0sjAoRtnM0jc", true).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn update_subscription(
+        &self,
+        iden: &str,
+        muted: bool,
+    ) -> Result<Subscription, RequestError> {
+        #[derive(Serialize)]
+        struct UpdateSubscription {
+            muted: bool,
+        }
+        let post_data = serde_json::to_string(&UpdateSubscription { muted }).unwrap();
+
+        let (bytes, data) = self
+            .post(&format!("subscriptions/{}", iden), post_data.into(), false)
+            .await?;
+        serde_json::from_value(data).map_err(|error| RequestError::Json { error, bytes })
+    }
+
+    /// Unsubscribes from a channel.
+    ///
+    /// Example usage:
+    ///
+    /// ```no_run
+    /// # async fn run() -> Result<(), pb_async::RequestError> {
+    /// # let client = pb_async::Client::new("...").unwrap();
+    /// client.delete_subscription("ujpah72o0sjAoRtnM0jc").await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn delete_subscription(&self, iden: &str) -> Result<(), RequestError> {
+        self.delete(&format!("subscriptions/{}", iden)).await?;
+        Ok(())
+    }
+
+    /// Retrieves information about a channel by tag, including its most
+    /// recent pushes.
+    ///
+    /// This does not require being subscribed to the channel.
+    ///
+    /// Example usage:
+    ///
+    /// ```no_run
+    /// # async fn run() -> Result<(), pb_async::RequestError> {
+    /// # let client = pb_async::Client::new("...").unwrap();
+    /// let info = client.channel_info("jblow").await?;
+    /// println!("{} has {} subscribers", info.name, info.subscriber_count);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn channel_info(&self, tag: &str) -> Result<ChannelInfo, RequestError> {
+        let query = url::form_urlencoded::Serializer::new(String::new())
+            .append_pair("tag", tag)
+            .finish();
+        let (bytes, data) = self.get_with_query("channel-info", query).await?;
+        serde_json::from_value(data).map_err(|error| RequestError::Json { error, bytes })
+    }
+
+    /// Retrieves a list of channels owned by the user.
+    ///
+    /// Example usage:
+    ///
+    /// ```no_run
+    /// # async fn run() -> Result<(), pb_async::RequestError> {
+    /// # let client = pb_async::Client::new("...").unwrap();
+    /// let channels = client.list_channels().await?;
+    /// println!("Owned channels: {:#?}", channels);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn list_channels(&self) -> Result<Vec<OwnedChannel>, RequestError> {
+        #[derive(Deserialize)]
+        struct Channels {
+            channels: Vec<OwnedChannel>,
+        }
+        let (bytes, data) = self.get("channels").await?;
+        let c: Channels =
+            serde_json::from_value(data).map_err(|error| RequestError::Json { error, bytes })?;
+        Ok(c.channels)
+    }
+
+    /// Creates a new channel owned by the user.
+    ///
+    /// Pushes can then be sent to all subscribers with
+    /// [`PushTarget::Channel`].
+    ///
+    /// Channel tags are globally unique. If the tag is already taken, this
+    /// fails with [`RequestError::Server`]. PushBullet doesn't document the
+    /// error code sent in that case, so use [`Client::channel_info`] to
+    /// tell a taken tag apart from other refusals.
+    ///
+    /// Example usage:
+    ///
+    /// ```no_run
+    /// use pb_async::RequestError;
+    ///
+    /// # async fn run() {
+    /// # let client = pb_async::Client::new("...").unwrap();
+    /// let channel = pb_async::ChannelParams {
+    ///     tag: "my-deployments",
+    ///     name: "Deployments",
+    ///     description: "Deployment announcements",
+    ///     ..Default::default()
+    /// };
+    ///
+    /// match client.create_channel(&channel).await {
+    ///     Ok(channel) => println!("Created channel {}", channel.iden),
+    ///     Err(RequestError::Server { code, message, .. }) => {
+    ///         if client.channel_info(channel.tag).await.is_ok() {
+    ///             eprintln!("{} is already taken", channel.tag);
+    ///         } else {
+    ///             eprintln!("server refused channel ({}): {}", code, message);
+    ///         }
+    ///     }
+    ///     Err(error) => eprintln!("error: {}", error),
+    /// }
+    /// # }
+    /// ```
+    pub async fn create_channel(
+        &self,
+        params: &ChannelParams<'_>,
+    ) -> Result<OwnedChannel, RequestError> {
+        let post_data = serde_json::to_string(params).unwrap();
+
+        let (bytes, data) = self.post("channels", post_data.into(), false).await?;
+        serde_json::from_value(data).map_err(|error| RequestError::Json { error, bytes })
+    }
+
+    /// Sends an SMS message through a phone.
+    ///
+    /// `device_iden` must be a device with [`Device.has_sms`] set. The
+    /// message is sent to all of `addresses`, which are phone numbers.
+    ///
+    /// This is shorthand for [`Client::create_text`] without an
+    /// attachment.
+    ///
+    /// Example usage:
+    ///
+    /// ```no_run
+    /// # async fn run() -> Result<(), pb_async::RequestError> {
+    /// # let client = pb_async::Client::new("...").unwrap();
+    /// client
+    ///     .send_sms("ujpah72o0sjAoRtnM0jc", &["+1 303 555 1212"], "Server is down!")
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [`Device.has_sms`]: Device::has_sms
+    pub async fn send_sms(
+        &self,
+        device_iden: &str,
+        addresses: &[&str],
+        message: &str,
+    ) -> Result<Text, RequestError> {
+        self.create_text(
+            &TextData {
+                target_device_iden: device_iden,
+                addresses,
+                message,
+                guid: None,
+                file_type: None,
+            },
+            None,
+        )
+        .await
     }
 
-    /// Pushes some data to a target.
+    /// Sends a text message through a phone, optionally with an attached
+    /// file.
     ///
-    /// Example usage:
+    /// If [`TextData.guid`] is set, the request is retried according to the
+    /// client's [`RetryPolicy`].
     ///
-    /// ```no_run
-    /// extern crate futures;
-    /// extern crate pb_async;
-    /// extern crate tokio;
+    /// To send an MMS, upload the file first with
+    /// [`Client::upload_request`], then pass its URL as `file_url` and its
+    /// MIME type as [`TextData.file_type`].
     ///
-    /// use futures::Future;
+    /// Example usage:
     ///
-    /// # fn main() {
+    /// ```no_run
+    /// # async fn run() -> Result<(), pb_async::RequestError> {
     /// # let client = pb_async::Client::new("...").unwrap();
-    ///
-    /// tokio::executor::spawn(
-    ///     client.push(
-    ///         pb_async::PushTarget::SelfUser {},
-    ///         pb_async::PushData::Note {
-    ///             title: "",
-    ///             body: "Hello, user!",
+    /// # let image_data = Vec::new();
+    /// let file_data = client
+    ///     .upload_request("graph.png", "image/png", image_data)
+    ///     .await?;
+    /// client
+    ///     .create_text(
+    ///         &pb_async::TextData {
+    ///             target_device_iden: "ujpah72o0sjAoRtnM0jc",
+    ///             addresses: &["+1 303 555 1212"],
+    ///             message: "Latency graph attached",
+    ///             guid: None,
+    ///             file_type: Some(&file_data.file_type),
     ///         },
-    ///     ).or_else(|error| {
-    ///         eprintln!("error: {}", error);
-    ///         Ok(())
-    ///     })
-    /// );
+    ///         Some(&file_data.file_url),
+    ///     )
+    ///     .await?;
+    /// # Ok(())
     /// # }
     /// ```
-    pub fn push(
+    ///
+    /// [`TextData.file_type`]: TextData::file_type
+    /// [`TextData.guid`]: TextData::guid
+    pub async fn create_text(
         &self,
-        target: PushTarget,
-        data: PushData,
-    ) -> impl Future<Item = (), Error = RequestError> {
+        data: &TextData<'_>,
+        file_url: Option<&str>,
+    ) -> Result<Text, RequestError> {
         #[derive(Serialize)]
-        struct Push<'a> {
-            #[serde(flatten)]
-            data: PushData<'a>,
-            #[serde(flatten)]
-            target: PushTarget<'a>,
+        struct CreateText<'a> {
+            data: &'a TextData<'a>,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            file_url: Option<&'a str>,
         }
+        let post_data = serde_json::to_string(&CreateText { data, file_url }).unwrap();
 
-        let post_data = serde_json::to_string(&Push { target, data }).unwrap();
-
-        self.post("pushes", post_data.into()).map(|_resp| ())
+        let idempotent = data.guid.is_some();
+        let (bytes, data) = self.post("texts", post_data.into(), idempotent).await?;
+        serde_json::from_value(data).map_err(|error| RequestError::Json { error, bytes })
     }
 
-    /// Prepares a file for upload prior to pushing it via [`Client::push`].
+    /// Deletes a text message.
     ///
-    /// This method handles file streaming correctly. If you use a streaming
-    /// [`hyper::Body`], it will be correctly wrapped and the resulting
-    /// connection won't need to keep the entire file in memory.
+    /// Messages which haven't yet been sent by the phone will be cancelled.
     ///
     /// Example usage:
     ///
     /// ```no_run
-    /// extern crate futures;
-    /// extern crate pb_async;
-    /// extern crate tokio;
-    ///
-    /// use futures::Future;
-    ///
-    /// # fn main() {
+    /// # async fn run() -> Result<(), pb_async::RequestError> {
     /// # let client = pb_async::Client::new("...").unwrap();
-    /// tokio::executor::spawn(
-    ///     client
-    ///         .upload_request("hello.txt", "text/plain", "Hello, world!\n".into())
-    ///         .and_then(move |file_data| {
-    ///             client.push(
-    ///                 pb_async::PushTarget::SelfUser {},
-    ///                 pb_async::PushData::File {
-    ///                     body: "",
-    ///                     file_name: &file_data.file_name,
-    ///                     file_type: &file_data.file_type,
-    ///                     file_url: &file_data.file_url,
-    ///                 },
-    ///             )
-    ///         })
-    ///         .or_else(|error| {
-    ///             eprintln!("error pushing file: {}", error);
-    ///             Ok(())
-    ///         }),
-    /// );
+    /// client.delete_text("ujpah72o0sjAoRtnM0jc").await?;
+    /// # Ok(())
     /// # }
     /// ```
-    pub fn upload_request(
-        &self,
-        file_name: &str,
-        file_type: &str,
-        upload_data: hyper::Body,
-    ) -> impl Future<Item = UploadRequestResponse, Error = RequestError> {
-        #[derive(Serialize)]
-        struct Upload<'a> {
-            file_name: &'a str,
-            file_type: &'a str,
-        }
-        let post_data = serde_json::to_string(&Upload {
-            file_name,
-            file_type,
-        }).unwrap();
-        let token_for_later_use = self.token.clone();
-        let client_for_later_use = self.client.clone();
-        self.post("upload-request", post_data.into())
-            .and_then(move |(bytes, data)| {
-                use http::header::*;
-                let RawUploadRequestResponse {
-                    file_name,
-                    file_type,
-                    file_url,
-                    upload_url,
-                } = serde_json::from_value(data)
-                    .map_err(|error| RequestError::Json { error, bytes })?;
-
-                let mut mpart = mpart_async::MultipartRequest::default();
-
-                mpart.add_stream(
-                    "file",
-                    &*file_name,
-                    &*file_type,
-                    upload_data.map(|chunk| chunk.into_bytes()),
-                );
-
-                let request = hyper::Request::post(upload_url)
-                    .header(TOKEN_HEADER, token_for_later_use)
-                    .header(
-                        CONTENT_TYPE,
-                        &*format!("multipart/form-data; boundary={}", mpart.get_boundary()),
-                    )
-                    .body(hyper::Body::wrap_stream(mpart))?;
-
-                Ok((
-                    request,
-                    UploadRequestResponse {
-                        file_name,
-                        file_type,
-                        file_url,
-                        _priv: (),
-                    },
-                ))
-            })
-            .and_then(move |(request, last_response)| {
-                client_for_later_use
-                    .request(request)
-                    .and_then(|response| {
-                        let (parts, body) = response.into_parts();
-                        body.concat2().map(|body| (parts, body))
-                    })
-                    .from_err()
-                    .and_then(|(parts, body)| {
-                        let bytes = body.into_bytes();
-                        if !parts.status.is_success() {
-                            return Err(RequestError::Status {
-                                status: parts.status,
-                                bytes: bytes,
-                            });
-                        }
-                        Ok(last_response)
-                    })
-            })
-    }
-
-    fn get(
+    pub async fn delete_text(&self, iden: &str) -> Result<(), RequestError> {
+        self.delete(&format!("texts/{}", iden)).await?;
+        Ok(())
+    }
+
+    async fn get(&self, target: &str) -> Result<(Bytes, serde_json::Value), RequestError> {
+        self.request(
+            target,
+            None,
+            RequestBody::default(),
+            http::Method::GET,
+            |b| b,
+        )
+        .await
+    }
+
+    async fn get_with_query(
         &self,
-        target: &'static str,
-    ) -> impl Future<Item = (bytes::Bytes, serde_json::Value), Error = RequestError> {
-        self.request(target, hyper::Body::empty(), http::Method::GET, |b| b)
+        target: &str,
+        query: String,
+    ) -> Result<(Bytes, serde_json::Value), RequestError> {
+        self.request(
+            target,
+            Some(query),
+            RequestBody::default(),
+            http::Method::GET,
+            |b| b,
+        )
+        .await
+    }
+
+    async fn delete(&self, target: &str) -> Result<(Bytes, serde_json::Value), RequestError> {
+        self.request(
+            target,
+            None,
+            RequestBody::default(),
+            http::Method::DELETE,
+            |b| b,
+        )
+        .await
     }
 
-    fn post(
+    /// Posts JSON data. `idempotent` marks requests which are safe to retry
+    /// after reaching the server.
+    async fn post(
         &self,
-        target: &'static str,
-        body: hyper::Body,
-    ) -> impl Future<Item = (bytes::Bytes, serde_json::Value), Error = RequestError> {
-        use hyper::body::Payload;
-        let length = body.content_length()
+        target: &str,
+        body: RequestBody,
+        idempotent: bool,
+    ) -> Result<(Bytes, serde_json::Value), RequestError> {
+        use hyper::body::Body;
+        let length = body
+            .size_hint()
+            .exact()
             .expect("expected unconditional content length");
-        self.request(target, body, http::Method::POST, move |b| {
-            b.header(http::header::CONTENT_TYPE, "application/json")
-                .header(http::header::CONTENT_LENGTH, &*format!("{}", length))
+        self.request(target, None, body, http::Method::POST, move |b| {
+            let b = b
+                .header(http::header::CONTENT_TYPE, "application/json")
+                .header(http::header::CONTENT_LENGTH, length);
+            if idempotent {
+                b.extension(retry::Idempotent)
+            } else {
+                b
+            }
         })
+        .await
     }
 
-    fn request(
+    async fn request(
         &self,
-        target: &'static str,
-        body: hyper::Body,
+        target: &str,
+        query: Option<String>,
+        body: RequestBody,
         method: http::Method,
-        extra: impl FnOnce(&mut http::request::Builder) -> &mut http::request::Builder,
-    ) -> impl Future<Item = (bytes::Bytes, serde_json::Value), Error = RequestError> {
+        extra: impl FnOnce(http::request::Builder) -> http::request::Builder,
+    ) -> Result<(Bytes, serde_json::Value), RequestError> {
+        let uri = match query {
+            Some(query) => format!("{}{}?{}", self.api_root, target, query),
+            None => format!("{}{}", self.api_root, target),
+        };
         let request = extra(
-            hyper::Request::builder()
+            http::Request::builder()
                 .method(method)
-                .uri(format!("{}{}", API_ROOT, target))
+                .uri(uri)
                 .header(TOKEN_HEADER, self.token.clone()),
-        ).body(body)
-            .expect("expected request to be well-formed");
-        debug!("sending request: {:?}", request);
-        self.client
-            .request(request)
-            .and_then(|response| {
-                let (parts, body) = response.into_parts();
-                body.concat2().map(|body| (parts, body))
-            })
-            .from_err()
-            .and_then(move |(parts, body)| {
-                let bytes = body.into_bytes();
-                let data: serde_json::Value =
-                    serde_json::from_slice(&*bytes).map_err(|error| RequestError::Json {
-                        error,
-                        bytes: bytes.clone(),
-                    })?;
-                debug!("received json: {:?} from {}", data, target);
-                if let Some(err_data) = data.as_object().and_then(|obj| obj.get("error")) {
-                    #[derive(Deserialize)]
-                    struct ErrorData {
-                        code: String,
-                        message: String,
-                    }
-                    if let Ok(ErrorData { code, message }) =
-                        serde::Deserialize::deserialize(err_data)
-                    {
-                        return Err(RequestError::Server { code, message });
-                    }
+        )
+        .body(body)
+        .expect("expected request to be well-formed");
+        send_request(&self.transport, request).await
+    }
+}
+
+/// Creates a hyper client, which only connects over HTTPS if `force_https`
+/// is set.
+fn https_client(force_https: bool) -> Result<HyperClient, StartupError> {
+    let tls = native_tls::TlsConnector::new().map_err(StartupError::Tls)?;
+    let mut http = HttpConnector::new();
+    http.enforce_http(false);
+    let mut connector = hyper_tls::HttpsConnector::from((http, tls.into()));
+    connector.https_only(force_https);
+    Ok(
+        hyper_util::client::legacy::Client::builder(hyper_util::rt::TokioExecutor::new())
+            .build(connector),
+    )
+}
+
+/// Hyper client used to make requests, along with settings applied to every
+/// request.
+#[derive(Clone)]
+struct Transport {
+    client: TransportClient,
+    /// Maximum time each request may take.
+    timeout: Option<Duration>,
+    /// Headers added to every request, such as the user agent.
+    headers: HeaderMap,
+    /// Policy for retrying failed requests.
+    retry: RetryPolicy,
+    /// Latest rate limit state reported by the server, shared between
+    /// clones.
+    rate_limit: Arc<Mutex<Option<RateLimit>>>,
+    /// Longest time to wait for an exhausted rate limit to reset before
+    /// sending a request, if waiting is enabled.
+    rate_limit_wait: Option<Duration>,
+}
+
+#[derive(Clone)]
+enum TransportClient {
+    Direct(HyperClient),
+    /// Client connecting through a proxy, along with headers which must be
+    /// sent to the proxy in plain HTTP requests.
+    Proxied(ProxiedClient, HeaderMap),
+}
+
+impl Transport {
+    fn new(client: HyperClient) -> Self {
+        Transport {
+            client: TransportClient::Direct(client),
+            timeout: None,
+            headers: HeaderMap::new(),
+            retry: RetryPolicy::none(),
+            rate_limit: Arc::new(Mutex::new(None)),
+            rate_limit_wait: None,
+        }
+    }
+
+    /// Retrieves the latest rate limit state reported by the server.
+    fn rate_limit(&self) -> Option<RateLimit> {
+        self.rate_limit.lock().unwrap().clone()
+    }
+
+    /// Waits for the rate limit to reset if it has been used up, as long as
+    /// that takes no longer than the configured maximum wait.
+    async fn wait_for_rate_limit(&self) {
+        let max_wait = match self.rate_limit_wait {
+            Some(max_wait) => max_wait,
+            None => return,
+        };
+        let delay = match self.rate_limit() {
+            Some(ref rate_limit) if rate_limit.is_exhausted() => rate_limit.reset_in(),
+            _ => return,
+        };
+        if delay.is_zero() || delay > max_wait {
+            return;
+        }
+        warn!("rate limit exhausted, waiting {:?} for it to reset", delay);
+        tokio::time::sleep(delay).await;
+    }
+
+    /// Sends a request and reads the response body, without otherwise
+    /// handling the response.
+    ///
+    /// Failed requests are retried according to the retry policy.
+    async fn request(
+        &self,
+        mut request: http::Request<RequestBody>,
+    ) -> Result<(http::response::Parts, Bytes), RequestError> {
+        for (name, value) in &self.headers {
+            if !request.headers().contains_key(name) {
+                request.headers_mut().insert(name, value.clone());
+            }
+        }
+        let idempotent = retry::is_idempotent(&request);
+        let uri = request.uri().clone();
+        let mut request = Some(request);
+        let mut attempt = 1;
+        loop {
+            self.wait_for_rate_limit().await;
+            // requests with streamed bodies can only be sent once, so are
+            // never retried
+            let current = request
+                .take()
+                .expect("expected request to be kept for retries");
+            let current = match clone_request(&current) {
+                Some(clone) => {
+                    request = Some(current);
+                    clone
+                }
+                None => current,
+            };
+            let result = self.attempt(current).await;
+            if let Ok((ref parts, _)) = result {
+                if let Some(rate_limit) = RateLimit::from_headers(&parts.headers) {
+                    *self.rate_limit.lock().unwrap() = Some(rate_limit);
                 }
-                if !parts.status.is_success() {
-                    return Err(RequestError::Status {
-                        status: parts.status,
-                        bytes: bytes,
-                    });
+            }
+            let delay = match request {
+                Some(_) => self.retry.retry_delay(attempt, &result, idempotent),
+                None => None,
+            };
+            let delay = match delay {
+                Some(delay) => delay,
+                None => return result,
+            };
+            match result {
+                Ok((ref parts, _)) => warn!(
+                    "retrying request to {} in {:?}: server responded {}",
+                    uri, delay, parts.status
+                ),
+                Err(ref e) => warn!("retrying request to {} in {:?}: {}", uri, delay, Chain(e)),
+            }
+            tokio::time::sleep(delay).await;
+            attempt += 1;
+        }
+    }
+
+    /// Makes a single attempt at sending a request, applying the timeout.
+    async fn attempt(
+        &self,
+        request: http::Request<RequestBody>,
+    ) -> Result<(http::response::Parts, Bytes), RequestError> {
+        match self.timeout {
+            Some(timeout) => tokio::time::timeout(timeout, self.send(request))
+                .await
+                .map_err(|_elapsed| RequestError::Timeout)?,
+            None => self.send(request).await,
+        }
+    }
+
+    async fn send(
+        &self,
+        mut request: http::Request<RequestBody>,
+    ) -> Result<(http::response::Parts, Bytes), RequestError> {
+        let response = match self.client {
+            TransportClient::Direct(ref client) => client.request(request).await?,
+            TransportClient::Proxied(ref client, ref proxy_headers) => {
+                // HTTPS requests are tunneled, and the proxy receives these
+                // headers when the tunnel is created
+                if request.uri().scheme_str() == Some("http") {
+                    request.headers_mut().extend(proxy_headers.clone());
                 }
-                Ok((bytes, data))
-            })
+                client.request(request).await?
+            }
+        };
+        let (parts, body) = response.into_parts();
+        let bytes = body.collect().await?.to_bytes();
+        Ok((parts, bytes))
+    }
+}
+
+/// Copies a request, so that it can be sent again, unless its body is
+/// streamed.
+fn clone_request(request: &http::Request<RequestBody>) -> Option<http::Request<RequestBody>> {
+    let mut clone = http::Request::new(request.body().try_clone()?);
+    *clone.method_mut() = request.method().clone();
+    *clone.uri_mut() = request.uri().clone();
+    *clone.version_mut() = request.version();
+    *clone.headers_mut() = request.headers().clone();
+    Some(clone)
+}
+
+/// Sends a request and parses the JSON response, handling errors returned
+/// by the server.
+async fn send_request(
+    transport: &Transport,
+    request: http::Request<RequestBody>,
+) -> Result<(Bytes, serde_json::Value), RequestError> {
+    debug!("sending request: {:?}", request);
+    let target = request.uri().path().to_owned();
+    let (parts, bytes) = transport.request(request).await?;
+    if parts.status == http::StatusCode::TOO_MANY_REQUESTS {
+        return Err(RequestError::RateLimited {
+            reset_at: ratelimit::reset_at(&parts.headers),
+        });
+    }
+    let data: serde_json::Value =
+        serde_json::from_slice(&bytes).map_err(|error| RequestError::Json {
+            error,
+            bytes: bytes.clone(),
+        })?;
+    debug!("received json: {:?} from {}", data, target);
+    if let Some(err_data) = data.as_object().and_then(|obj| obj.get("error")) {
+        #[derive(Deserialize)]
+        struct ErrorData {
+            code: ServerErrorCode,
+            message: String,
+            cat: Option<String>,
+        }
+        if let Ok(ErrorData { code, message, cat }) = ErrorData::deserialize(err_data) {
+            return Err(RequestError::Server {
+                status: parts.status,
+                code,
+                message,
+                cat,
+            });
+        }
+    }
+    if !parts.status.is_success() {
+        return Err(RequestError::Status {
+            status: parts.status,
+            bytes,
+        });
     }
+    Ok((bytes, data))
+}
+
+/// Creates the framing of a `multipart/form-data` body with a single `file`
+/// field, returning the boundary used along with the data to send before and
+/// after the file contents.
+fn multipart_framing(file_name: &str, file_type: &str) -> (String, Bytes, Bytes) {
+    let boundary = random_hex();
+    let file_name = file_name
+        .replace('"', "%22")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A");
+
+    let head = format!(
+        "--{}\r\nContent-Disposition: form-data; name=\"file\"; filename=\"{}\"\r\n\
+         Content-Type: {}\r\n\r\n",
+        boundary, file_name, file_type
+    );
+    let tail = format!("\r\n--{}--\r\n", boundary);
+    (boundary, head.into(), tail.into())
+}
+
+/// Generates a random 32 character hex string, for use as a unique
+/// identifier.
+fn random_hex() -> String {
+    let mut random = [0u8; 16];
+    ring::rand::SystemRandom::new()
+        .fill(&mut random)
+        .expect("expected system random number generator to succeed");
+    random.iter().map(|byte| format!("{:02x}", byte)).collect()
 }
 
 /// Target which data can be pushed to.
@@ -432,13 +1425,15 @@ pub enum PushTarget<'a> {
     },
     /// Send to all subscribers in a channel by tag.
     Channel {
-        /// Channel tag. No way to retrieve this in current crate API.
+        /// Channel tag - see [Channel.tag], [Client::list_subscriptions] and
+        /// [Client::list_channels].
         #[serde(rename = "channel_tag")]
         tag: &'a str,
     },
     /// Send to all users who have granted access to an OAuth by iden.
     Client {
-        /// OAuth client iden. No way to retrieve this in current crate API.
+        /// OAuth client iden. See [OAuthClientInfo.iden] and
+        /// [Client::list_clients].
         #[serde(rename = "client_iden")]
         iden: &'a str,
     },
@@ -518,10 +1513,577 @@ pub struct Device {
     pub modified: f64,
     /// Nickname of device
     pub nickname: Option<String>,
+    /// Whether the nickname was generated by the server rather than set by
+    /// the user.
+    #[serde(default)]
+    pub generated_nickname: bool,
+    /// Manufacturer of the device.
+    pub manufacturer: Option<String>,
+    /// Model of the device.
+    pub model: Option<String>,
+    /// Icon used when displaying the device.
+    pub icon: Option<DeviceIcon>,
+    /// Version of the PushBullet app running on the device.
+    pub app_version: Option<u32>,
+    /// Unique fingerprint of the device, set by the device's app.
+    pub fingerprint: Option<String>,
+    /// Fingerprint of the device's end-to-end encryption key, if end-to-end
+    /// encryption is enabled.
+    pub key_fingerprint: Option<String>,
+    /// Platform-specific push token.
+    pub push_token: Option<String>,
+    /// Whether or not the device can send SMS messages.
+    #[serde(default)]
+    pub has_sms: bool,
+    /// Kind of device (legacy field, prefer `icon`).
+    pub kind: Option<String>,
+    /// Type of device (legacy field, prefer `icon`).
+    #[serde(rename = "type")]
+    pub device_type: Option<String>,
+    #[serde(default)]
+    _priv: (),
+}
+
+/// Icon used when displaying a [`Device`].
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum DeviceIcon {
+    /// Desktop computer.
+    Desktop,
+    /// Web browser.
+    Browser,
+    /// Website.
+    Website,
+    /// Laptop computer.
+    Laptop,
+    /// Tablet.
+    Tablet,
+    /// Phone.
+    Phone,
+    /// Smart watch.
+    Watch,
+    /// System (server or other headless device).
+    System,
+    /// Icon not known to this crate.
+    Other(String),
+}
+
+impl DeviceIcon {
+    /// Retrieves the string used for this icon in the PushBullet API.
+    pub fn as_str(&self) -> &str {
+        match *self {
+            DeviceIcon::Desktop => "desktop",
+            DeviceIcon::Browser => "browser",
+            DeviceIcon::Website => "website",
+            DeviceIcon::Laptop => "laptop",
+            DeviceIcon::Tablet => "tablet",
+            DeviceIcon::Phone => "phone",
+            DeviceIcon::Watch => "watch",
+            DeviceIcon::System => "system",
+            DeviceIcon::Other(ref icon) => icon,
+        }
+    }
+}
+
+impl serde::Serialize for DeviceIcon {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for DeviceIcon {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let icon = String::deserialize(deserializer)?;
+        Ok(match &*icon {
+            "desktop" => DeviceIcon::Desktop,
+            "browser" => DeviceIcon::Browser,
+            "website" => DeviceIcon::Website,
+            "laptop" => DeviceIcon::Laptop,
+            "tablet" => DeviceIcon::Tablet,
+            "phone" => DeviceIcon::Phone,
+            "watch" => DeviceIcon::Watch,
+            "system" => DeviceIcon::System,
+            _ => DeviceIcon::Other(icon),
+        })
+    }
+}
+
+/// Chat with another user or email address.
+#[derive(Clone, Debug, Deserialize)]
+pub struct Chat {
+    /// Whether or not this chat is active.
+    ///
+    /// Deleted chats show up as non-active.
+    pub active: bool,
+    /// Creation timestamp in unix time.
+    pub created: f64,
+    /// Chat identifier.
+    pub iden: String,
+    /// Modified timestamp in unix time.
+    pub modified: f64,
+    /// Whether or not notifications from this chat are muted.
+    #[serde(default)]
+    pub muted: bool,
+    /// The user or email address this chat is with.
+    pub with: ChatContact,
+    #[serde(default)]
+    _priv: (),
+}
+
+/// The other side of a [`Chat`].
+#[derive(Clone, Debug, Deserialize)]
+pub struct ChatContact {
+    /// Email address - can be used as a push target in [`PushTarget::User`].
+    pub email: String,
+    /// Normalized email address.
+    pub email_normalized: String,
+    /// User identifier, if this contact is a PushBullet user.
+    pub iden: Option<String>,
+    /// URL of profile image.
+    pub image_url: Option<String>,
+    /// Real name, if known.
+    pub name: Option<String>,
+    /// Whether this contact is a PushBullet user or just an email address.
+    #[serde(rename = "type")]
+    pub contact_type: ChatContactType,
+    #[serde(default)]
+    _priv: (),
+}
+
+/// Type of a [`ChatContact`].
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum ChatContactType {
+    /// Email address without a PushBullet account.
+    Email,
+    /// PushBullet user.
+    User,
+    /// Contact type not known to this crate.
+    Other(String),
+}
+
+impl ChatContactType {
+    /// Retrieves the string used for this contact type in the PushBullet API.
+    pub fn as_str(&self) -> &str {
+        match *self {
+            ChatContactType::Email => "email",
+            ChatContactType::User => "user",
+            ChatContactType::Other(ref other) => other,
+        }
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for ChatContactType {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let value = String::deserialize(deserializer)?;
+        Ok(match &*value {
+            "email" => ChatContactType::Email,
+            "user" => ChatContactType::User,
+            _ => ChatContactType::Other(value),
+        })
+    }
+}
+
+/// Subscription to a channel.
+#[derive(Clone, Debug, Deserialize)]
+pub struct Subscription {
+    /// Whether or not this subscription is active.
+    ///
+    /// Deleted subscriptions show up as non-active.
+    pub active: bool,
+    /// Creation timestamp in unix time.
+    pub created: f64,
+    /// Subscription identifier.
+    pub iden: String,
+    /// Modified timestamp in unix time.
+    pub modified: f64,
+    /// Whether or not notifications from this channel are muted.
+    #[serde(default)]
+    pub muted: bool,
+    /// The channel subscribed to.
+    pub channel: Channel,
+    #[serde(default)]
+    _priv: (),
+}
+
+/// PushBullet channel, as seen in a [`Subscription`].
+#[derive(Clone, Debug, Deserialize)]
+pub struct Channel {
+    /// Channel identifier.
+    pub iden: String,
+    /// Channel tag - used as a push target in [`PushTarget::Channel`].
+    pub tag: String,
+    /// Channel name.
+    pub name: String,
+    /// Channel description.
+    pub description: Option<String>,
+    /// URL of channel image.
+    pub image_url: Option<String>,
+    /// URL of website associated with the channel.
+    pub website_url: Option<String>,
+    #[serde(default)]
+    _priv: (),
+}
+
+/// Information about a channel, returned by [`Client::channel_info`].
+#[derive(Clone, Debug, Deserialize)]
+pub struct ChannelInfo {
+    /// Channel identifier.
+    pub iden: String,
+    /// Channel tag - used as a push target in [`PushTarget::Channel`].
+    pub tag: String,
+    /// Channel name.
+    pub name: String,
+    /// Channel description.
+    pub description: Option<String>,
+    /// URL of channel image.
+    pub image_url: Option<String>,
+    /// URL of website associated with the channel.
+    pub website_url: Option<String>,
+    /// Number of users subscribed to this channel.
+    #[serde(default)]
+    pub subscriber_count: u64,
+    /// Most recent pushes sent to this channel.
+    #[serde(default)]
+    pub recent_pushes: Vec<Push>,
+    #[serde(default)]
+    _priv: (),
+}
+
+/// Channel owned by the user, returned by [`Client::list_channels`] and
+/// [`Client::create_channel`].
+#[derive(Clone, Debug, Deserialize)]
+pub struct OwnedChannel {
+    /// Whether or not this channel is active.
+    ///
+    /// Deleted channels show up as non-active.
+    pub active: bool,
+    /// Creation timestamp in unix time.
+    pub created: f64,
+    /// Channel identifier.
+    pub iden: String,
+    /// Modified timestamp in unix time.
+    pub modified: f64,
+    /// Channel tag - used as a push target in [`PushTarget::Channel`].
+    pub tag: String,
+    /// Channel name.
+    pub name: String,
+    /// Channel description.
+    pub description: Option<String>,
+    /// URL of channel image.
+    pub image_url: Option<String>,
+    /// URL of website associated with the channel.
+    pub website_url: Option<String>,
+    /// URL of an RSS/Atom feed which PushBullet pushes new items from.
+    pub feed_url: Option<String>,
+    /// Filters applied to feed items before they are pushed.
+    #[serde(default)]
+    pub feed_filters: Vec<FeedFilter>,
+    #[serde(default)]
+    _priv: (),
+}
+
+/// Channel information used in [`Client::create_channel`].
+///
+/// Optional fields left as `None` are not sent. Use `..Default::default()`
+/// to leave out those which aren't needed.
+#[derive(Serialize, Copy, Clone, Debug, Default)]
+pub struct ChannelParams<'a> {
+    /// Unique tag for the channel.
+    pub tag: &'a str,
+    /// Channel name.
+    pub name: &'a str,
+    /// Channel description.
+    pub description: &'a str,
+    /// URL of channel image.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub image_url: Option<&'a str>,
+    /// URL of website associated with the channel.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub website_url: Option<&'a str>,
+    /// URL of an RSS/Atom feed to push new items from.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub feed_url: Option<&'a str>,
+    /// Filters to apply to feed items before they are pushed.
+    #[serde(skip_serializing_if = "<[_]>::is_empty")]
+    pub feed_filters: &'a [FeedFilter],
+}
+
+/// Filter for items in a channel's RSS/Atom feed.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct FeedFilter {
+    /// Feed item field to match against, such as `"title"`.
+    pub field: String,
+    /// Comparison to make, such as `"contains"`.
+    pub operator: String,
+    /// Value to compare the field with.
+    pub value: String,
+    /// Whether to invert the filter.
+    #[serde(default)]
+    pub not: bool,
+    /// Whether to ignore case when comparing.
+    #[serde(default)]
+    pub ignore_case: bool,
+}
+
+/// Text message information used in [`Client::create_text`].
+#[derive(Serialize, Copy, Clone, Debug)]
+pub struct TextData<'a> {
+    /// Device to send the message from - see [`Device.has_sms`].
+    ///
+    /// [`Device.has_sms`]: Device::has_sms
+    pub target_device_iden: &'a str,
+    /// Phone numbers to send the message to.
+    pub addresses: &'a [&'a str],
+    /// Text of the message.
+    pub message: &'a str,
+    /// Unique identifier set by the client, used to recognize the message
+    /// later.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub guid: Option<&'a str>,
+    /// MIME type of the attached file, if any.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub file_type: Option<&'a str>,
+}
+
+/// Text message sent through a phone, returned by [`Client::create_text`].
+#[derive(Clone, Debug, Deserialize)]
+pub struct Text {
+    /// Whether or not this text is active.
+    ///
+    /// Deleted texts show up as non-active.
+    pub active: bool,
+    /// Creation timestamp in unix time.
+    pub created: f64,
+    /// Text identifier.
+    pub iden: String,
+    /// Modified timestamp in unix time.
+    pub modified: f64,
+    /// Message details.
+    pub data: TextInfo,
+    /// URL of the attached file, if any.
+    pub file_url: Option<String>,
+    #[serde(default)]
+    _priv: (),
+}
+
+/// Details of a [`Text`].
+#[derive(Clone, Debug, Deserialize)]
+pub struct TextInfo {
+    /// Device the message is sent from.
+    pub target_device_iden: String,
+    /// Phone numbers the message is sent to.
+    pub addresses: Vec<String>,
+    /// Text of the message.
+    pub message: String,
+    /// Unique identifier set by the client which created this text.
+    pub guid: Option<String>,
+    /// MIME type of the attached file, if any.
+    pub file_type: Option<String>,
+    #[serde(default)]
+    _priv: (),
+}
+
+/// Parameters for [`Client::list_pushes`] and [`Client::all_pushes`].
+///
+/// All fields are optional. Use `..Default::default()` to leave out those
+/// which aren't needed.
+#[derive(Clone, Debug, Default)]
+pub struct ListPushes {
+    /// Only request active pushes when `Some(true)`.
+    ///
+    /// Deleted pushes are inactive, and only their `iden`, `active`,
+    /// `created` and `modified` fields will be available.
+    pub active: Option<bool>,
+    /// Only request pushes modified after this unix timestamp.
+    pub modified_after: Option<f64>,
+    /// Maximum number of pushes to return in one page.
+    pub limit: Option<u32>,
+    /// Cursor returned from a previous [`PushPage`].
+    pub cursor: Option<String>,
+}
+
+/// One page of push history, returned by [`Client::list_pushes`].
+#[derive(Clone, Debug, Deserialize)]
+pub struct PushPage {
+    /// Pushes in this page, most recently modified first.
+    pub pushes: Vec<Push>,
+    /// Cursor to retrieve the next page with, if any.
+    pub cursor: Option<String>,
+    #[serde(default)]
+    _priv: (),
+}
+
+/// A push, as stored by PushBullet.
+#[derive(Clone, Debug, Deserialize)]
+pub struct Push {
+    /// Push identifier.
+    pub iden: String,
+    /// Whether or not this push is active.
+    ///
+    /// Deleted pushes show up as non-active, and have no other data besides
+    /// identifier and timestamps.
+    pub active: bool,
+    /// Creation timestamp in unix time.
+    pub created: f64,
+    /// Modified timestamp in unix time.
+    pub modified: f64,
+    /// Type of push.
+    #[serde(rename = "type")]
+    pub push_type: Option<PushType>,
+    /// Whether or not this push has been dismissed.
+    #[serde(default)]
+    pub dismissed: bool,
+    /// Direction of this push relative to the current user.
+    pub direction: Option<PushDirection>,
+    /// Identifier of the user who sent this push.
+    pub sender_iden: Option<String>,
+    /// Email of the user who sent this push.
+    pub sender_email: Option<String>,
+    /// Normalized email of the user who sent this push.
+    pub sender_email_normalized: Option<String>,
+    /// Name of the user who sent this push.
+    pub sender_name: Option<String>,
+    /// Identifier of the user who received this push.
+    pub receiver_iden: Option<String>,
+    /// Email of the user who received this push.
+    pub receiver_email: Option<String>,
+    /// Normalized email of the user who received this push.
+    pub receiver_email_normalized: Option<String>,
+    /// Unique identifier set by the client which created this push.
+    pub guid: Option<String>,
+    /// Device this push was sent to, if it was sent to a specific device.
+    pub target_device_iden: Option<String>,
+    /// Device this push was sent from, if any.
+    pub source_device_iden: Option<String>,
+    /// OAuth client this push was sent to, if it was sent to all users of
+    /// an OAuth client.
+    pub client_iden: Option<String>,
+    /// Channel this push was sent to, if it was sent to a channel.
+    pub channel_iden: Option<String>,
+    /// Title of a note or link push.
+    pub title: Option<String>,
+    /// Message of a note, link or file push.
+    pub body: Option<String>,
+    /// URL of a link push.
+    pub url: Option<String>,
+    /// File name of a file push.
+    pub file_name: Option<String>,
+    /// MIME type of a file push.
+    pub file_type: Option<String>,
+    /// URL of a file push's file.
+    pub file_url: Option<String>,
+    /// URL of a preview image for a file push, if the file is an image.
+    pub image_url: Option<String>,
+    /// Width of the image at `image_url`.
+    pub image_width: Option<u32>,
+    /// Height of the image at `image_url`.
+    pub image_height: Option<u32>,
     #[serde(default)]
     _priv: (),
 }
 
+/// Changes to make to a push in [`Client::update_push`].
+///
+/// Fields left as `None` are not changed.
+#[derive(Serialize, Clone, Debug, Default)]
+pub struct PushUpdate {
+    /// Mark the push as dismissed or not dismissed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dismissed: Option<bool>,
+}
+
+/// Type of a [`Push`].
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum PushType {
+    /// Note push.
+    Note,
+    /// Link push.
+    Link,
+    /// File push.
+    File,
+    /// Push type not known to this crate, such as the legacy `address` and `list` types.
+    Other(String),
+}
+
+impl PushType {
+    /// Retrieves the string used for this push type in the PushBullet API.
+    pub fn as_str(&self) -> &str {
+        match *self {
+            PushType::Note => "note",
+            PushType::Link => "link",
+            PushType::File => "file",
+            PushType::Other(ref other) => other,
+        }
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for PushType {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let value = String::deserialize(deserializer)?;
+        Ok(match &*value {
+            "note" => PushType::Note,
+            "link" => PushType::Link,
+            "file" => PushType::File,
+            _ => PushType::Other(value),
+        })
+    }
+}
+
+/// Direction of a [`Push`] relative to the current user.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PushDirection {
+    /// Push sent from the user to themselves.
+    #[serde(rename = "self")]
+    SelfUser,
+    /// Push sent from the user to someone else.
+    Outgoing,
+    /// Push sent from someone else to the user.
+    Incoming,
+}
+
+/// Device information used in [`Client::create_device`] and
+/// [`Client::update_device`].
+///
+/// Fields left as `None` are not sent. Use `..Default::default()` to leave
+/// out those which aren't needed.
+#[derive(Serialize, Copy, Clone, Debug, Default)]
+pub struct DeviceParams<'a> {
+    /// Name to use when displaying the device.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub nickname: Option<&'a str>,
+    /// Model of the device.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub model: Option<&'a str>,
+    /// Manufacturer of the device.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub manufacturer: Option<&'a str>,
+    /// Platform-specific push token, if the device receives pushes through
+    /// some other service.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub push_token: Option<&'a str>,
+    /// Version of the app running on the device.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub app_version: Option<u32>,
+    /// Icon to use for the device.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub icon: Option<&'a DeviceIcon>,
+    /// Whether or not the device can send SMS messages.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub has_sms: Option<bool>,
+}
+
 /// (raw) response to [`Client::upload_request`].
 ///
 /// This is separate since it has the 'upload_url' field we consume.
@@ -546,3 +2108,63 @@ pub struct UploadRequestResponse {
     pub file_url: String,
     _priv: (),
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unknown_push_type() {
+        let page: PushPage = serde_json::from_value(serde_json::json!({
+            "pushes": [
+                {"iden": "a", "active": true, "created": 1.0, "modified": 1.0, "type": "note"},
+                {"iden": "b", "active": true, "created": 1.0, "modified": 1.0, "type": "address"},
+            ],
+        }))
+        .unwrap();
+        assert_eq!(page.pushes[0].push_type, Some(PushType::Note));
+        assert_eq!(
+            page.pushes[1].push_type,
+            Some(PushType::Other("address".to_owned()))
+        );
+        assert_eq!(
+            page.pushes[1].push_type.as_ref().unwrap().as_str(),
+            "address"
+        );
+    }
+
+    #[test]
+    fn unknown_chat_contact_type() {
+        let contact: ChatContact = serde_json::from_value(serde_json::json!({
+            "email": "someone@example.com",
+            "email_normalized": "someone@example.com",
+            "type": "group",
+        }))
+        .unwrap();
+        assert_eq!(
+            contact.contact_type,
+            ChatContactType::Other("group".to_owned())
+        );
+    }
+
+    #[test]
+    fn device_params_icon() {
+        let other = DeviceIcon::Other("toaster".to_owned());
+        let params = DeviceParams {
+            icon: Some(&other),
+            ..Default::default()
+        };
+        assert_eq!(
+            serde_json::to_value(params).unwrap(),
+            serde_json::json!({ "icon": "toaster" })
+        );
+        let params = DeviceParams {
+            icon: Some(&DeviceIcon::Phone),
+            ..Default::default()
+        };
+        assert_eq!(
+            serde_json::to_value(params).unwrap(),
+            serde_json::json!({ "icon": "phone" })
+        );
+    }
+}
@@ -0,0 +1,290 @@
+//! OAuth, for retrieving access tokens on behalf of other users.
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    https_client, send_request, Client, HyperClient, RequestError, StartupError, Transport,
+};
+
+static AUTHORIZE_URL: &str = "https://www.pushbullet.com/authorize";
+static TOKEN_URL: &str = "https://api.pushbullet.com/oauth2/token";
+
+/// OAuth client, for acting on behalf of other PushBullet users.
+///
+/// Create an OAuth client in [the PushBullet account settings] to get a
+/// client id and secret. Then send users to
+/// [`OAuthClient::authorize_url`], and exchange the code they return with
+/// for an access token using [`OAuthClient::exchange_code`].
+///
+/// [the PushBullet account settings]: https://www.pushbullet.com/#settings/clients
+pub struct OAuthClient {
+    client_id: String,
+    client_secret: String,
+    token_url: String,
+    transport: Transport,
+}
+
+impl OAuthClient {
+    /// Create a new OAuth client with a given client id and secret.
+    ///
+    /// Example usage:
+    ///
+    /// ```no_run
+    /// let client = pb_async::OAuthClient::new("client-id", "client-secret")
+    ///     .expect("expected client creation to succeed");
+    /// ```
+    pub fn new(client_id: &str, client_secret: &str) -> Result<Self, StartupError> {
+        Ok(OAuthClient::with_client(client_id, client_secret, https_client(true)?))
+    }
+
+    /// Create a new OAuth client with a given client id and secret and an
+    /// existing hyper client.
+    pub fn with_client(client_id: &str, client_secret: &str, client: HyperClient) -> Self {
+        OAuthClient {
+            client_id: client_id.to_owned(),
+            client_secret: client_secret.to_owned(),
+            token_url: TOKEN_URL.to_owned(),
+            transport: Transport::new(client),
+        }
+    }
+
+    /// Sets the URL authorization codes are exchanged at in
+    /// [`OAuthClient::exchange_code`].
+    ///
+    /// Defaults to `https://api.pushbullet.com/oauth2/token`. Clients created
+    /// with [`OAuthClient::new`] only connect over HTTPS, so testing against
+    /// a local `http://` server requires a client created with
+    /// [`OAuthClient::with_client`].
+    pub fn token_url(mut self, token_url: &str) -> Self {
+        self.token_url = token_url.to_owned();
+        self
+    }
+
+    /// Creates the URL to send a user to in order to authorize this client.
+    ///
+    /// After authorizing, the user is redirected to `redirect_uri`, which
+    /// must match the redirect URI configured for the client. With
+    /// [`ResponseType::Code`], the redirect includes a `code` query
+    /// parameter to use in [`OAuthClient::exchange_code`]. With
+    /// [`ResponseType::Token`], the redirect includes an `access_token` in
+    /// the URL fragment.
+    ///
+    /// `state`, if given, is passed back unchanged in the redirect.
+    ///
+    /// Example usage:
+    ///
+    /// ```no_run
+    /// # let client = pb_async::OAuthClient::new("client-id", "client-secret").unwrap();
+    /// let url = client.authorize_url(
+    ///     "https://example.com/pushbullet-callback",
+    ///     pb_async::ResponseType::Code,
+    ///     Some("session-1234"),
+    /// );
+    /// println!("Visit {} to grant access", url);
+    /// ```
+    pub fn authorize_url(
+        &self,
+        redirect_uri: &str,
+        response_type: ResponseType,
+        state: Option<&str>,
+    ) -> String {
+        let mut url = url::Url::parse(AUTHORIZE_URL).expect("expected valid authorize url");
+        {
+            let mut query = url.query_pairs_mut();
+            query
+                .append_pair("client_id", &self.client_id)
+                .append_pair("redirect_uri", redirect_uri)
+                .append_pair("response_type", response_type.as_str());
+            if let Some(state) = state {
+                query.append_pair("state", state);
+            }
+        }
+        url.into()
+    }
+
+    /// Exchanges an authorization code for an access token.
+    ///
+    /// The resulting token can be used with [`Client::new`].
+    ///
+    /// Example usage:
+    ///
+    /// ```no_run
+    /// # async fn run() -> Result<(), pb_async::RequestError> {
+    /// # let oauth_client = pb_async::OAuthClient::new("client-id", "client-secret").unwrap();
+    /// let token = oauth_client.exchange_code("code-from-redirect").await?;
+    /// let client = pb_async::Client::new(&token.access_token).unwrap();
+    /// let user = client.get_user().await?;
+    /// println!("Authorized by {}", user.email);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn exchange_code(&self, code: &str) -> Result<AccessToken, RequestError> {
+        #[derive(Serialize)]
+        struct TokenRequest<'a> {
+            grant_type: &'static str,
+            client_id: &'a str,
+            client_secret: &'a str,
+            code: &'a str,
+        }
+        let post_data = serde_json::to_string(&TokenRequest {
+            grant_type: "authorization_code",
+            client_id: &self.client_id,
+            client_secret: &self.client_secret,
+            code,
+        })
+        .unwrap();
+
+        let request = http::Request::post(&*self.token_url)
+            .header(http::header::CONTENT_TYPE, "application/json")
+            .header(http::header::CONTENT_LENGTH, post_data.len())
+            .body(post_data.into())?;
+        let (bytes, data) = send_request(&self.transport, request).await?;
+        serde_json::from_value(data).map_err(|error| RequestError::Json { error, bytes })
+    }
+}
+
+impl Client {
+    /// Retrieves the OAuth clients the user has granted access to their
+    /// account.
+    ///
+    /// Example usage:
+    ///
+    /// ```no_run
+    /// # async fn run() -> Result<(), pb_async::RequestError> {
+    /// # let client = pb_async::Client::new("...").unwrap();
+    /// for grant in client.list_grants().await? {
+    ///     println!("Access granted to {}", grant.client.name);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn list_grants(&self) -> Result<Vec<Grant>, RequestError> {
+        #[derive(Deserialize)]
+        struct Grants {
+            grants: Vec<Grant>,
+        }
+        let (bytes, data) = self.get("grants").await?;
+        let g: Grants =
+            serde_json::from_value(data).map_err(|error| RequestError::Json { error, bytes })?;
+        Ok(g.grants)
+    }
+
+    /// Retrieves the OAuth clients owned by the user.
+    ///
+    /// Pushes can be sent to all users who have granted access to a client
+    /// with [`PushTarget::Client`].
+    ///
+    /// Example usage:
+    ///
+    /// ```no_run
+    /// # async fn run() -> Result<(), pb_async::RequestError> {
+    /// # let client = pb_async::Client::new("...").unwrap();
+    /// let clients = client.list_clients().await?;
+    /// let app = clients.into_iter().next().expect("no OAuth clients");
+    /// client
+    ///     .push(
+    ///         pb_async::PushTarget::Client { iden: &app.iden },
+    ///         pb_async::PushData::Note {
+    ///             title: "Hello",
+    ///             body: "Hello to all users of this app",
+    ///         },
+    ///     )
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [`PushTarget::Client`]: crate::PushTarget::Client
+    pub async fn list_clients(&self) -> Result<Vec<OAuthClientInfo>, RequestError> {
+        #[derive(Deserialize)]
+        struct Clients {
+            clients: Vec<OAuthClientInfo>,
+        }
+        let (bytes, data) = self.get("clients").await?;
+        let c: Clients =
+            serde_json::from_value(data).map_err(|error| RequestError::Json { error, bytes })?;
+        Ok(c.clients)
+    }
+}
+
+/// Access granted to an OAuth client, returned by [`Client::list_grants`].
+#[derive(Clone, Debug, Deserialize)]
+pub struct Grant {
+    /// Whether or not this grant is active.
+    ///
+    /// Revoked grants show up as non-active.
+    pub active: bool,
+    /// Creation timestamp in unix time.
+    pub created: f64,
+    /// Grant identifier.
+    pub iden: String,
+    /// Modified timestamp in unix time.
+    pub modified: f64,
+    /// The client access was granted to.
+    pub client: OAuthClientInfo,
+    #[serde(default)]
+    _priv: (),
+}
+
+/// OAuth client, returned by [`Client::list_clients`] and as part of a
+/// [`Grant`].
+#[derive(Clone, Debug, Deserialize)]
+pub struct OAuthClientInfo {
+    /// Client identifier - used as a push target in [`PushTarget::Client`].
+    ///
+    /// [`PushTarget::Client`]: crate::PushTarget::Client
+    pub iden: String,
+    /// Client name.
+    pub name: String,
+    /// URL of client image.
+    pub image_url: Option<String>,
+    /// URL of website associated with the client.
+    pub website_url: Option<String>,
+    /// Whether or not this client is active.
+    ///
+    /// Only present for clients owned by the user.
+    pub active: Option<bool>,
+    /// Creation timestamp in unix time.
+    ///
+    /// Only present for clients owned by the user.
+    pub created: Option<f64>,
+    /// Modified timestamp in unix time.
+    ///
+    /// Only present for clients owned by the user.
+    pub modified: Option<f64>,
+    /// URI users are redirected to after authorizing the client.
+    ///
+    /// Only present for clients owned by the user.
+    pub redirect_uri: Option<String>,
+    #[serde(default)]
+    _priv: (),
+}
+
+/// Response type requested in [`OAuthClient::authorize_url`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ResponseType {
+    /// Redirect with an authorization code, for use on servers - see
+    /// [`OAuthClient::exchange_code`].
+    Code,
+    /// Redirect with an access token, for use in client-side applications.
+    Token,
+}
+
+impl ResponseType {
+    fn as_str(&self) -> &'static str {
+        match *self {
+            ResponseType::Code => "code",
+            ResponseType::Token => "token",
+        }
+    }
+}
+
+/// Access token, returned by [`OAuthClient::exchange_code`].
+#[derive(Clone, Debug, Deserialize)]
+pub struct AccessToken {
+    /// The token - used with [`Client::new`].
+    pub access_token: String,
+    /// Type of token. Always `"Bearer"`.
+    pub token_type: String,
+    #[serde(default)]
+    _priv: (),
+}
@@ -0,0 +1,211 @@
+//! Permanents: data stored by PushBullet on behalf of a device, such as SMS
+//! history.
+use serde::Deserialize;
+
+use crate::encryption::decrypt_value;
+use crate::{Client, Device, RequestError};
+
+impl Client {
+    /// Retrieves the SMS conversations on a phone.
+    ///
+    /// `device` should be a phone with [`Device.has_sms`] set - see
+    /// [`Client::list_devices`].
+    ///
+    /// If end-to-end encryption is enabled on the phone, this requires the
+    /// client to be configured with [`Client::set_encryption`].
+    ///
+    /// Example usage:
+    ///
+    /// ```no_run
+    /// # async fn run() -> Result<(), pb_async::RequestError> {
+    /// # let client = pb_async::Client::new("...").unwrap();
+    /// let devices = client.list_devices().await?;
+    /// let phone = devices.into_iter().find(|d| d.has_sms).expect("no phone");
+    /// let threads = client.list_sms_threads(&phone).await?;
+    /// println!("SMS threads: {:#?}", threads);
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [`Device.has_sms`]: Device::has_sms
+    pub async fn list_sms_threads(&self, device: &Device) -> Result<Vec<SmsThread>, RequestError> {
+        #[derive(Deserialize)]
+        struct Threads {
+            threads: Vec<SmsThread>,
+        }
+        let (bytes, data) = self
+            .get(&format!("permanents/{}_threads", device.iden))
+            .await?;
+        let data =
+            decrypt_value(data, self.encryption.as_deref()).map_err(RequestError::Encryption)?;
+        let t: Threads =
+            serde_json::from_value(data).map_err(|error| RequestError::Json { error, bytes })?;
+        Ok(t.threads)
+    }
+
+    /// Retrieves the messages in an SMS conversation on a phone.
+    ///
+    /// `thread_id` is an [`SmsThread.id`] from
+    /// [`Client::list_sms_threads`]. Messages are returned most recent
+    /// first.
+    ///
+    /// If end-to-end encryption is enabled on the phone, this requires the
+    /// client to be configured with [`Client::set_encryption`].
+    ///
+    /// Example usage:
+    ///
+    /// ```no_run
+    /// # async fn run() -> Result<(), pb_async::RequestError> {
+    /// # let client = pb_async::Client::new("...").unwrap();
+    /// let devices = client.list_devices().await?;
+    /// let phone = devices.into_iter().find(|d| d.has_sms).expect("no phone");
+    /// for message in client.list_sms_messages(&phone, "3").await? {
+    ///     println!("{:?}: {}", message.direction, message.body);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [`SmsThread.id`]: SmsThread::id
+    pub async fn list_sms_messages(
+        &self,
+        device: &Device,
+        thread_id: &str,
+    ) -> Result<Vec<SmsMessage>, RequestError> {
+        #[derive(Deserialize)]
+        struct Thread {
+            thread: Vec<SmsMessage>,
+        }
+        let (bytes, data) = self
+            .get(&format!("permanents/{}_thread_{}", device.iden, thread_id))
+            .await?;
+        let data =
+            decrypt_value(data, self.encryption.as_deref()).map_err(RequestError::Encryption)?;
+        let t: Thread =
+            serde_json::from_value(data).map_err(|error| RequestError::Json { error, bytes })?;
+        Ok(t.thread)
+    }
+}
+
+/// SMS conversation on a phone, returned by [`Client::list_sms_threads`].
+#[derive(Clone, Debug, Deserialize)]
+pub struct SmsThread {
+    /// Thread identifier, used in [`Client::list_sms_messages`].
+    pub id: String,
+    /// Other participants in the conversation.
+    pub recipients: Vec<SmsRecipient>,
+    /// Most recent message in the conversation.
+    pub latest: Option<SmsMessage>,
+    #[serde(default)]
+    _priv: (),
+}
+
+/// Participant in an [`SmsThread`].
+#[derive(Clone, Debug, Deserialize)]
+pub struct SmsRecipient {
+    /// Contact name, or the phone number if the contact has no name.
+    pub name: String,
+    /// Phone number, as formatted by the phone.
+    pub address: String,
+    /// Phone number, normalized.
+    pub number: String,
+    /// URL of contact image, if any.
+    pub image_url: Option<String>,
+    #[serde(default)]
+    _priv: (),
+}
+
+/// Message in an SMS conversation, returned by [`Client::list_sms_messages`].
+#[derive(Clone, Debug, Deserialize)]
+pub struct SmsMessage {
+    /// Message identifier.
+    pub id: String,
+    /// Whether this is an SMS or MMS message.
+    #[serde(rename = "type")]
+    pub message_type: SmsMessageType,
+    /// Timestamp in unix time.
+    pub timestamp: f64,
+    /// Whether the message was sent or received by the phone.
+    pub direction: SmsDirection,
+    /// Text of the message.
+    #[serde(default)]
+    pub body: String,
+    /// Sending status reported by the phone, if any.
+    pub status: Option<String>,
+    /// Index into [`SmsThread.recipients`] of the message sender, for
+    /// incoming messages in group conversations.
+    ///
+    /// [`SmsThread.recipients`]: SmsThread::recipients
+    pub recipient_index: Option<usize>,
+    /// URLs of images attached to an MMS message.
+    #[serde(default)]
+    pub image_urls: Vec<String>,
+    #[serde(default)]
+    _priv: (),
+}
+
+/// Type of an [`SmsMessage`].
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum SmsMessageType {
+    /// Text message.
+    Sms,
+    /// Multimedia message.
+    Mms,
+    /// Message type not known to this crate.
+    Other(String),
+}
+
+impl SmsMessageType {
+    /// Retrieves the string used for this message type in the PushBullet API.
+    pub fn as_str(&self) -> &str {
+        match *self {
+            SmsMessageType::Sms => "sms",
+            SmsMessageType::Mms => "mms",
+            SmsMessageType::Other(ref other) => other,
+        }
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for SmsMessageType {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let value = String::deserialize(deserializer)?;
+        Ok(match &*value {
+            "sms" => SmsMessageType::Sms,
+            "mms" => SmsMessageType::Mms,
+            _ => SmsMessageType::Other(value),
+        })
+    }
+}
+
+/// Direction of an [`SmsMessage`] relative to the phone.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SmsDirection {
+    /// Message received by the phone.
+    Incoming,
+    /// Message sent from the phone.
+    Outgoing,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unknown_message_type() {
+        let message: SmsMessage = serde_json::from_value(serde_json::json!({
+            "id": "1",
+            "type": "rcs",
+            "timestamp": 1.0,
+            "direction": "incoming",
+        }))
+        .unwrap();
+        assert_eq!(
+            message.message_type,
+            SmsMessageType::Other("rcs".to_owned())
+        );
+    }
+}
@@ -0,0 +1,62 @@
+//! Tracking of the API rate limit reported by the server.
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use http::header::HeaderMap;
+
+static LIMIT_HEADER: &str = "X-Ratelimit-Limit";
+static REMAINING_HEADER: &str = "X-Ratelimit-Remaining";
+static RESET_HEADER: &str = "X-Ratelimit-Reset";
+
+/// Rate limit state reported by the server, retrieved with
+/// [`Client::rate_limit`](crate::Client::rate_limit).
+///
+/// PushBullet limits how much each account may use the API in a period of
+/// time, measured in "quota units". Each request uses some of the units
+/// remaining in the current period.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RateLimit {
+    /// Total quota units available in each period.
+    pub limit: u64,
+    /// Quota units remaining in the current period.
+    pub remaining: u64,
+    /// Time at which the current period ends and the quota is reset, in
+    /// seconds since the unix epoch.
+    pub reset_at: f64,
+    _priv: (),
+}
+
+impl RateLimit {
+    /// Parses the rate limit headers of a response, if all are present.
+    pub(crate) fn from_headers(headers: &HeaderMap) -> Option<Self> {
+        Some(RateLimit {
+            limit: header(headers, LIMIT_HEADER)?.parse().ok()?,
+            remaining: header(headers, REMAINING_HEADER)?.parse().ok()?,
+            reset_at: reset_at(headers)?,
+            _priv: (),
+        })
+    }
+
+    /// Whether all of the quota for the current period has been used.
+    pub fn is_exhausted(&self) -> bool {
+        self.remaining == 0
+    }
+
+    /// Time remaining until the quota is reset, or zero if the reset time
+    /// has already passed.
+    pub fn reset_in(&self) -> Duration {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs_f64();
+        Duration::try_from_secs_f64(self.reset_at - now).unwrap_or_default()
+    }
+}
+
+/// Parses the rate limit reset time of a response.
+pub(crate) fn reset_at(headers: &HeaderMap) -> Option<f64> {
+    header(headers, RESET_HEADER)?.parse().ok()
+}
+
+fn header<'a>(headers: &'a HeaderMap, name: &str) -> Option<&'a str> {
+    Some(headers.get(name)?.to_str().ok()?.trim())
+}
@@ -0,0 +1,183 @@
+//! Automatic retries of requests which fail transiently.
+use std::time::{Duration, SystemTime};
+
+use bytes::Bytes;
+use http::header::{HeaderMap, RETRY_AFTER};
+use ring::rand::SecureRandom;
+
+use crate::RequestError;
+
+/// Policy for retrying requests which fail transiently, set with
+/// [`ClientBuilder::retry_policy`](crate::ClientBuilder::retry_policy).
+///
+/// Requests are retried after connection errors, timeouts and `5xx`
+/// responses, waiting between attempts with exponential backoff. If the
+/// server sends a `Retry-After` header, that delay is used instead - or, if
+/// it is longer than the maximum delay, the request is not retried.
+///
+/// Only requests which are safe to repeat are retried after reaching the
+/// server: `GET` and `DELETE` requests, and pushes, which are sent with a
+/// generated guid so that the server ignores duplicates. Other requests
+/// are only retried when the connection could not be established, and so
+/// the request was never sent.
+///
+/// Example usage:
+///
+/// ```no_run
+/// use std::time::Duration;
+///
+/// let policy = pb_async::RetryPolicy::new()
+///     .max_attempts(5)
+///     .base_delay(Duration::from_millis(200))
+///     .max_delay(Duration::from_secs(10));
+///
+/// let client = pb_async::Client::builder("...")
+///     .retry_policy(policy)
+///     .build()
+///     .expect("expected client creation to succeed");
+/// ```
+#[derive(Clone, Debug)]
+pub struct RetryPolicy {
+    max_attempts: u32,
+    base_delay: Duration,
+    max_delay: Duration,
+    jitter: bool,
+}
+
+impl RetryPolicy {
+    /// Creates a policy making up to 3 attempts, with delays starting at
+    /// 500 milliseconds and at most 30 seconds, with jitter.
+    pub fn new() -> Self {
+        RetryPolicy {
+            max_attempts: 3,
+            base_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(30),
+            jitter: true,
+        }
+    }
+
+    /// Creates a policy which never retries requests.
+    ///
+    /// This is the default for clients.
+    pub fn none() -> Self {
+        RetryPolicy::new().max_attempts(1)
+    }
+
+    /// Sets the maximum number of attempts made for each request, including
+    /// the first.
+    ///
+    /// Values less than 1 are treated as 1.
+    pub fn max_attempts(mut self, max_attempts: u32) -> Self {
+        self.max_attempts = max_attempts.max(1);
+        self
+    }
+
+    /// Sets the delay before the first retry. Each following retry waits
+    /// twice as long as the one before.
+    pub fn base_delay(mut self, delay: Duration) -> Self {
+        self.base_delay = delay;
+        self
+    }
+
+    /// Sets the maximum delay between attempts.
+    pub fn max_delay(mut self, delay: Duration) -> Self {
+        self.max_delay = delay;
+        self
+    }
+
+    /// Sets whether delays are randomized, to avoid many clients retrying
+    /// at the same moment.
+    ///
+    /// With jitter, each delay is chosen at random between half and all of
+    /// the backoff delay.
+    pub fn jitter(mut self, jitter: bool) -> Self {
+        self.jitter = jitter;
+        self
+    }
+
+    /// Whether this policy retries any requests.
+    pub(crate) fn is_enabled(&self) -> bool {
+        self.max_attempts > 1
+    }
+
+    /// Decides whether to retry after the given attempt, returning the
+    /// delay to wait before the next attempt.
+    ///
+    /// `attempt` starts at 1 for the first attempt.
+    pub(crate) fn retry_delay(
+        &self,
+        attempt: u32,
+        result: &Result<(http::response::Parts, Bytes), RequestError>,
+        idempotent: bool,
+    ) -> Option<Duration> {
+        if attempt >= self.max_attempts {
+            return None;
+        }
+        let retry_after = match *result {
+            Ok((ref parts, _)) if parts.status.is_server_error() && idempotent => {
+                retry_after(&parts.headers)
+            }
+            // the request was never sent, so it's always safe to retry
+            Err(RequestError::Client(ref error)) if error.is_connect() => None,
+            Err(RequestError::Client(_))
+            | Err(RequestError::Hyper(_))
+            | Err(RequestError::Timeout)
+                if idempotent =>
+            {
+                None
+            }
+            _ => return None,
+        };
+        match retry_after {
+            Some(delay) if delay > self.max_delay => None,
+            Some(delay) => Some(delay),
+            None => Some(self.backoff(attempt)),
+        }
+    }
+
+    /// Delay before the retry following the given attempt.
+    fn backoff(&self, attempt: u32) -> Duration {
+        let delay = self
+            .base_delay
+            .checked_mul(1 << (attempt - 1).min(31))
+            .map_or(self.max_delay, |delay| delay.min(self.max_delay));
+        if self.jitter {
+            let mut random = [0u8; 4];
+            ring::rand::SystemRandom::new()
+                .fill(&mut random)
+                .expect("expected system random number generator to succeed");
+            let fraction = f64::from(u32::from_le_bytes(random)) / f64::from(u32::MAX);
+            delay / 2 + (delay / 2).mul_f64(fraction)
+        } else {
+            delay
+        }
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy::new()
+    }
+}
+
+/// Marks a request as safe to send more than once, in request extensions.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Idempotent;
+
+/// Whether a request is safe to send more than once.
+pub(crate) fn is_idempotent<B>(request: &http::Request<B>) -> bool {
+    match *request.method() {
+        http::Method::GET | http::Method::HEAD | http::Method::DELETE => true,
+        _ => request.extensions().get::<Idempotent>().is_some(),
+    }
+}
+
+/// Parses a `Retry-After` header, given either in seconds or as a date.
+fn retry_after(headers: &HeaderMap) -> Option<Duration> {
+    let value = headers.get(RETRY_AFTER)?.to_str().ok()?.trim();
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+    let date = httpdate::parse_http_date(value).ok()?;
+    Some(date.duration_since(SystemTime::now()).unwrap_or_default())
+}
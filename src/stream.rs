@@ -0,0 +1,243 @@
+//! Realtime event stream, using the PushBullet websocket API.
+use std::future::Future;
+use std::pin::Pin;
+use std::time::Duration;
+
+use futures_util::stream::{self, Stream, StreamExt, TryStreamExt};
+use serde::Deserialize;
+use tokio_tungstenite::connect_async;
+use tokio_tungstenite::tungstenite::Message;
+
+use crate::encryption::{ciphertext, decrypt_value};
+use crate::{Client, Encryption, Ephemeral, StreamError};
+
+pub(crate) static STREAM_ROOT: &str = "wss://stream.pushbullet.com/websocket/";
+
+/// PushBullet sends a nop every 30 seconds. We allow a few seconds of leeway
+/// before considering the connection dead.
+pub(crate) const HEARTBEAT_TIMEOUT: u64 = 35;
+
+impl Client {
+    /// Connects to the realtime event stream.
+    ///
+    /// The stream yields an [`Event`] for each message received from
+    /// PushBullet. If no message is received for over 30 seconds, the
+    /// stream yields [`StreamError::MissedHeartbeat`] and ends; when this
+    /// happens, the connection should be considered dead and a new stream
+    /// created. Websocket errors also end the stream, while messages which
+    /// fail to parse or decrypt are yielded as errors without ending it.
+    ///
+    /// If end-to-end encryption is configured with
+    /// [`Client::set_encryption`], encrypted ephemerals are decrypted.
+    ///
+    /// Example usage:
+    ///
+    /// ```no_run
+    /// use futures_util::StreamExt;
+    ///
+    /// # async fn run() {
+    /// # let client = pb_async::Client::new("...").unwrap();
+    /// let mut events = Box::pin(client.stream());
+    /// while let Some(event) = events.next().await {
+    ///     match event {
+    ///         Ok(event) => println!("Event: {:?}", event),
+    ///         Err(error) => eprintln!("error: {}", error),
+    ///     }
+    /// }
+    /// # }
+    /// ```
+    pub fn stream(&self) -> impl Stream<Item = Result<Event, StreamError>> + Send + 'static {
+        stream::once(self.connect_stream()).try_flatten()
+    }
+
+    /// Connects to the realtime event stream, resolving once the websocket
+    /// connection is established.
+    pub(crate) fn connect_stream(
+        &self,
+    ) -> impl Future<Output = Result<EventStream, StreamError>> + Send + 'static {
+        let url = self
+            .token
+            .to_str()
+            .map_err(|_| StreamError::InvalidToken)
+            .and_then(|token| {
+                url::Url::parse(&format!("{}{}", self.stream_root, token))
+                    .map_err(StreamError::Url)
+            });
+        let encryption = self.encryption.clone();
+        async move {
+            let (socket, _response) = connect_async(url?.as_str())
+                .await
+                .map_err(StreamError::WebSocket)?;
+            let events = stream::unfold(Some(socket), move |socket| {
+                let encryption = encryption.clone();
+                async move {
+                    let mut socket = socket?;
+                    loop {
+                        let timeout = Duration::from_secs(HEARTBEAT_TIMEOUT);
+                        let text = match tokio::time::timeout(timeout, socket.next()).await {
+                            Err(_elapsed) => {
+                                return Some((Err(StreamError::MissedHeartbeat), None))
+                            }
+                            Ok(None) => return None,
+                            Ok(Some(Err(error))) => {
+                                return Some((Err(StreamError::WebSocket(error)), None))
+                            }
+                            Ok(Some(Ok(Message::Text(text)))) => text,
+                            Ok(Some(Ok(_))) => continue,
+                        };
+                        debug!("received stream message: {}", text);
+                        let event = Event::from_message(text.to_string(), encryption.as_deref());
+                        return Some((event, Some(socket)));
+                    }
+                }
+            });
+            Ok(Box::pin(events) as EventStream)
+        }
+    }
+}
+
+pub(crate) type EventStream = Pin<Box<dyn Stream<Item = Result<Event, StreamError>> + Send>>;
+
+/// Event received from [`Client::stream`].
+#[derive(Clone, Debug)]
+pub enum Event {
+    /// Heartbeat, sent every 30 seconds.
+    Nop,
+    /// Notification that some server-side data has changed.
+    Tickle(Tickle),
+    /// Ephemeral message, sent from another device or from PushBullet.
+    ///
+    /// Ephemerals are not stored, and can only be received while connected
+    /// to the stream.
+    Ephemeral(Ephemeral),
+}
+
+/// Kind of data which has changed, sent in [`Event::Tickle`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Tickle {
+    /// Pushes have changed - see [`Client::list_pushes`] with
+    /// [`ListPushes.modified_after`].
+    ///
+    /// [`ListPushes.modified_after`]: crate::ListPushes::modified_after
+    Push,
+    /// Devices have changed - see [`Client::list_devices`].
+    Device,
+    /// Some other data has changed.
+    Other(String),
+}
+
+impl Event {
+    fn from_message(text: String, encryption: Option<&Encryption>) -> Result<Self, StreamError> {
+        #[derive(Deserialize)]
+        #[serde(tag = "type")]
+        #[serde(rename_all = "snake_case")]
+        enum RawEvent {
+            Nop,
+            Tickle { subtype: String },
+            Push { push: serde_json::Value },
+        }
+
+        let raw = match serde_json::from_str(&text) {
+            Ok(raw) => raw,
+            Err(error) => return Err(StreamError::Json { error, text }),
+        };
+        Ok(match raw {
+            RawEvent::Nop => Event::Nop,
+            RawEvent::Tickle { subtype } => Event::Tickle(match &*subtype {
+                "push" => Tickle::Push,
+                "device" => Tickle::Device,
+                _ => Tickle::Other(subtype),
+            }),
+            RawEvent::Push { push } => {
+                if encryption.is_none() {
+                    if let Some(ciphertext) = ciphertext(&push).map(|c| c.to_owned()) {
+                        return Ok(Event::Ephemeral(Ephemeral::Encrypted(ciphertext)));
+                    }
+                }
+                let push = decrypt_value(push, encryption).map_err(StreamError::Encryption)?;
+                match Ephemeral::from_value(push) {
+                    Ok(ephemeral) => Event::Ephemeral(ephemeral),
+                    Err(error) => return Err(StreamError::Json { error, text }),
+                }
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(message: serde_json::Value) -> Event {
+        Event::from_message(message.to_string(), None).unwrap()
+    }
+
+    #[test]
+    fn nop() {
+        let nop = event(serde_json::json!({"type": "nop"}));
+        assert!(matches!(nop, Event::Nop));
+    }
+
+    #[test]
+    fn tickles() {
+        let tickle = |subtype| event(serde_json::json!({"type": "tickle", "subtype": subtype}));
+        assert!(matches!(tickle("push"), Event::Tickle(Tickle::Push)));
+        assert!(matches!(tickle("device"), Event::Tickle(Tickle::Device)));
+        match tickle("account") {
+            Event::Tickle(Tickle::Other(subtype)) => assert_eq!(subtype, "account"),
+            other => panic!("expected other tickle, found {:?}", other),
+        }
+    }
+
+    #[test]
+    fn ephemerals() {
+        let clip = event(serde_json::json!({
+            "type": "push",
+            "push": {"type": "clip", "body": "copied", "source_user_iden": "user"},
+        }));
+        match clip {
+            Event::Ephemeral(Ephemeral::Clip(clip)) => assert_eq!(clip.body, "copied"),
+            other => panic!("expected clip, found {:?}", other),
+        }
+
+        let unknown = event(serde_json::json!({
+            "type": "push",
+            "push": {"type": "sms_changed", "notifications": []},
+        }));
+        match unknown {
+            Event::Ephemeral(Ephemeral::Other(value)) => assert_eq!(value["type"], "sms_changed"),
+            other => panic!("expected other ephemeral, found {:?}", other),
+        }
+    }
+
+    #[test]
+    fn encrypted() {
+        let encryption = Encryption::new("hunter2", "user");
+        let push = encryption.encrypt_value(&serde_json::json!({
+            "type": "clip",
+            "body": "secret",
+            "source_user_iden": "user",
+        }));
+        let message = serde_json::json!({"type": "push", "push": push}).to_string();
+
+        match Event::from_message(message.clone(), None) {
+            Ok(Event::Ephemeral(Ephemeral::Encrypted(ciphertext))) => {
+                assert_eq!(ciphertext, push["ciphertext"]);
+            }
+            other => panic!("expected encrypted ephemeral, found {:?}", other),
+        }
+        match Event::from_message(message, Some(&encryption)) {
+            Ok(Event::Ephemeral(Ephemeral::Clip(clip))) => assert_eq!(clip.body, "secret"),
+            other => panic!("expected clip, found {:?}", other),
+        }
+    }
+
+    #[test]
+    fn invalid() {
+        let text = r#"{"type": "unknown"}"#.to_owned();
+        match Event::from_message(text, None) {
+            Err(StreamError::Json { text, .. }) => assert_eq!(text, r#"{"type": "unknown"}"#),
+            other => panic!("expected json error, found {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,421 @@
+//! Auto-reconnecting stream of changes, built on the realtime event stream.
+use std::cmp;
+use std::collections::VecDeque;
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use futures_util::stream::{Stream, TryStreamExt};
+use tokio::time::Sleep;
+
+use crate::errors::Chain;
+use crate::stream::{EventStream, HEARTBEAT_TIMEOUT};
+use crate::{
+    Client, Device, Ephemeral, Event, ListPushes, Push, RequestError, StreamError, Tickle,
+};
+
+/// Delay before the first reconnection attempt.
+const INITIAL_BACKOFF: u64 = 1;
+/// Maximum delay between reconnection attempts.
+const MAX_BACKOFF: u64 = 300;
+
+impl Client {
+    /// Creates a stream of changes to pushes and devices.
+    ///
+    /// This connects to the realtime event stream, and fetches changed
+    /// pushes or devices whenever the server notifies us of a change. All
+    /// pushes modified after `modified_after` are yielded, including those
+    /// changed while disconnected. The full list of devices is yielded
+    /// after each connection, and again whenever it changes.
+    ///
+    /// If the connection is lost or can't be established, or a request
+    /// fails due to a network or server error, the stream reconnects with
+    /// exponential backoff, and resumes from the most recent `modified`
+    /// timestamp seen. Responses which can't be parsed are skipped until the
+    /// next change. Errors are logged, and never end the stream.
+    ///
+    /// Example usage:
+    ///
+    /// ```no_run
+    /// use futures_util::StreamExt;
+    /// use pb_async::SyncEvent;
+    ///
+    /// # async fn run() {
+    /// # let client = pb_async::Client::new("...").unwrap();
+    /// let mut changes = client.sync(0.0);
+    /// while let Some(event) = changes.next().await {
+    ///     match event {
+    ///         SyncEvent::Push(push) => println!("Push changed: {:?}", push),
+    ///         SyncEvent::Devices(devices) => println!("Devices: {:?}", devices),
+    ///         SyncEvent::Ephemeral(ephemeral) => println!("Ephemeral: {:?}", ephemeral),
+    ///     }
+    /// }
+    /// # }
+    /// ```
+    pub fn sync(&self, modified_after: f64) -> SyncStream {
+        SyncStream {
+            client: self.clone(),
+            modified_after,
+            backoff: Duration::from_secs(INITIAL_BACKOFF),
+            queued: VecDeque::new(),
+            reconnect_delay: None,
+            connecting: None,
+            events: None,
+            pending_pushes: false,
+            pending_devices: false,
+            push_fetch: None,
+            device_fetch: None,
+        }
+    }
+}
+
+/// Change yielded by [`SyncStream`].
+#[derive(Clone, Debug)]
+pub enum SyncEvent {
+    /// A push was created, modified or deleted.
+    ///
+    /// Pushes are yielded in the order they were modified.
+    Push(Push),
+    /// Devices have changed. Contains the full, updated list of devices.
+    Devices(Vec<Device>),
+    /// An ephemeral was received.
+    Ephemeral(Ephemeral),
+}
+
+/// Stream of changes, created with [`Client::sync`].
+///
+/// This stream never fails nor ends.
+pub struct SyncStream {
+    client: Client,
+    modified_after: f64,
+    backoff: Duration,
+    queued: VecDeque<SyncEvent>,
+    reconnect_delay: Option<Pin<Box<Sleep>>>,
+    connecting: Option<BoxFuture<Result<EventStream, StreamError>>>,
+    events: Option<EventStream>,
+    pending_pushes: bool,
+    pending_devices: bool,
+    push_fetch: Option<BoxFuture<Result<Vec<Push>, RequestError>>>,
+    device_fetch: Option<BoxFuture<Result<Vec<Device>, RequestError>>>,
+}
+
+type BoxFuture<T> = Pin<Box<dyn Future<Output = T> + Send>>;
+
+impl SyncStream {
+    /// Drops the current connection, and schedules a reconnect.
+    fn reconnect_later(&mut self) {
+        self.events = None;
+        self.connecting = None;
+        info!("reconnecting to stream in {}s", self.backoff.as_secs());
+        self.reconnect_delay = Some(Box::pin(tokio::time::sleep(self.backoff)));
+        self.backoff = cmp::min(self.backoff * 2, Duration::from_secs(MAX_BACKOFF));
+    }
+
+    /// Starts connecting to the realtime event stream. Connecting fails if
+    /// the handshake doesn't complete within the heartbeat interval, so a
+    /// stalled server can't hold up reconnecting.
+    fn connect(&mut self) {
+        let connect = self.client.connect_stream();
+        self.connecting = Some(Box::pin(async move {
+            tokio::time::timeout(Duration::from_secs(HEARTBEAT_TIMEOUT), connect)
+                .await
+                .unwrap_or(Err(StreamError::ConnectTimeout))
+        }));
+    }
+
+    /// Polls the websocket connection, returning true if any progress was
+    /// made.
+    fn poll_connection(&mut self, cx: &mut Context) -> bool {
+        if let Some(mut delay) = self.reconnect_delay.take() {
+            if delay.as_mut().poll(cx).is_pending() {
+                self.reconnect_delay = Some(delay);
+                return false;
+            }
+            self.connect();
+            return true;
+        }
+        if let Some(mut connecting) = self.connecting.take() {
+            match connecting.as_mut().poll(cx) {
+                Poll::Pending => {
+                    self.connecting = Some(connecting);
+                    return false;
+                }
+                Poll::Ready(Ok(events)) => {
+                    debug!("connected to stream");
+                    self.events = Some(events);
+                    // catch up on anything we missed while disconnected
+                    self.pending_pushes = true;
+                    self.pending_devices = true;
+                }
+                Poll::Ready(Err(e)) => {
+                    warn!("error connecting to stream: {}", Chain(&e));
+                    self.reconnect_later();
+                }
+            }
+            return true;
+        }
+        let mut events = match self.events.take() {
+            Some(events) => events,
+            None => {
+                self.connect();
+                return true;
+            }
+        };
+        match events.as_mut().poll_next(cx) {
+            Poll::Pending => {
+                self.events = Some(events);
+                false
+            }
+            Poll::Ready(Some(Ok(event))) => {
+                self.backoff = Duration::from_secs(INITIAL_BACKOFF);
+                self.events = Some(events);
+                match event {
+                    Event::Nop => {}
+                    Event::Tickle(Tickle::Push) => self.pending_pushes = true,
+                    Event::Tickle(Tickle::Device) => self.pending_devices = true,
+                    Event::Tickle(Tickle::Other(_)) => {}
+                    Event::Ephemeral(ephemeral) => {
+                        self.queued.push_back(SyncEvent::Ephemeral(ephemeral));
+                    }
+                }
+                true
+            }
+            Poll::Ready(Some(Err(StreamError::Json { error, text }))) => {
+                warn!("ignoring invalid stream message: {} (message: {:?})", error, text);
+                self.events = Some(events);
+                true
+            }
+            Poll::Ready(Some(Err(StreamError::Encryption(e)))) => {
+                warn!(
+                    "ignoring stream message which failed to decrypt: {}",
+                    Chain(&e)
+                );
+                self.events = Some(events);
+                true
+            }
+            Poll::Ready(None) => {
+                warn!("stream closed by server");
+                self.reconnect_later();
+                true
+            }
+            Poll::Ready(Some(Err(e))) => {
+                warn!("error in stream: {}", Chain(&e));
+                self.reconnect_later();
+                true
+            }
+        }
+    }
+
+    /// Polls requests for changed data, returning true if any progress was
+    /// made.
+    fn poll_fetches(&mut self, cx: &mut Context) -> bool {
+        let mut progress = false;
+        let mut reconnect = false;
+        let connected = self.events.is_some();
+
+        if self.push_fetch.is_none() && self.pending_pushes && connected {
+            self.pending_pushes = false;
+            let params = ListPushes {
+                modified_after: Some(self.modified_after),
+                ..Default::default()
+            };
+            self.push_fetch = Some(Box::pin(self.client.all_pushes(params).try_collect()));
+        }
+        if let Some(mut fetch) = self.push_fetch.take() {
+            match fetch.as_mut().poll(cx) {
+                Poll::Pending => self.push_fetch = Some(fetch),
+                Poll::Ready(Ok(pushes)) => {
+                    // pushes are retrieved most recently modified first
+                    for push in pushes.into_iter().rev() {
+                        if push.modified > self.modified_after {
+                            self.modified_after = push.modified;
+                        }
+                        self.queued.push_back(SyncEvent::Push(push));
+                    }
+                    progress = true;
+                }
+                Poll::Ready(Err(e)) if is_transient(&e) => {
+                    warn!("error retrieving pushes: {}", Chain(&e));
+                    self.pending_pushes = true;
+                    reconnect = true;
+                    progress = true;
+                }
+                Poll::Ready(Err(e)) => {
+                    // retrying would fail the same way, so wait for the next
+                    // change instead
+                    error!(
+                        "skipping pushes which could not be retrieved: {}",
+                        Chain(&e)
+                    );
+                    progress = true;
+                }
+            }
+        }
+
+        if self.device_fetch.is_none() && self.pending_devices && connected && !reconnect {
+            self.pending_devices = false;
+            let client = self.client.clone();
+            self.device_fetch = Some(Box::pin(async move { client.list_devices().await }));
+        }
+        if let Some(mut fetch) = self.device_fetch.take() {
+            match fetch.as_mut().poll(cx) {
+                Poll::Pending => self.device_fetch = Some(fetch),
+                Poll::Ready(Ok(devices)) => {
+                    self.queued.push_back(SyncEvent::Devices(devices));
+                    progress = true;
+                }
+                Poll::Ready(Err(e)) if is_transient(&e) => {
+                    warn!("error retrieving devices: {}", Chain(&e));
+                    self.pending_devices = true;
+                    reconnect = true;
+                    progress = true;
+                }
+                Poll::Ready(Err(e)) => {
+                    error!(
+                        "skipping devices which could not be retrieved: {}",
+                        Chain(&e)
+                    );
+                    progress = true;
+                }
+            }
+        }
+
+        // both fetches may fail, but the connection should only be dropped
+        // once - the fetches are retried after reconnecting either way
+        if reconnect && self.events.is_some() {
+            self.reconnect_later();
+        }
+        progress
+    }
+}
+
+/// Whether a request failed due to a problem which may go away by itself,
+/// such as a network error, rather than a problem with the response.
+fn is_transient(error: &RequestError) -> bool {
+    match *error {
+        RequestError::Client(_)
+        | RequestError::Hyper(_)
+        | RequestError::Timeout
+        | RequestError::RateLimited { .. } => true,
+        RequestError::Status { status, .. } | RequestError::Server { status, .. } => {
+            status.is_server_error()
+        }
+        _ => false,
+    }
+}
+
+impl Stream for SyncStream {
+    type Item = SyncEvent;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<SyncEvent>> {
+        let this = self.get_mut();
+        loop {
+            if let Some(event) = this.queued.pop_front() {
+                return Poll::Ready(Some(event));
+            }
+            let connection_progress = this.poll_connection(cx);
+            let fetch_progress = this.poll_fetches(cx);
+            if !connection_progress && !fetch_progress {
+                return Poll::Pending;
+            }
+        }
+    }
+}
+
+#[cfg(all(test, feature = "test-server"))]
+mod tests {
+    use futures_util::stream::{self, StreamExt};
+    use http::StatusCode;
+    use tokio::sync::mpsc;
+
+    use super::*;
+    use crate::testing::MockServer;
+    use crate::{PushData, PushTarget};
+
+    /// Creates a sync stream which receives events from the returned sender
+    /// rather than connecting to the realtime event stream.
+    fn sync(client: &Client) -> (SyncStream, mpsc::UnboundedSender<Event>) {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        let events = stream::unfold(receiver, |mut receiver| async move {
+            let event = receiver.recv().await?;
+            Some((Ok(event), receiver))
+        });
+        let mut sync = client.sync(0.0);
+        sync.events = Some(Box::pin(events));
+        (sync, sender)
+    }
+
+    async fn push(client: &Client, body: &str) -> Push {
+        client
+            .push(PushTarget::SelfUser {}, PushData::Note { title: "", body })
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn tickle_fetches_pushes() {
+        let server = MockServer::start("token").unwrap();
+        let client = server.client().unwrap();
+        let (mut sync, events) = sync(&client);
+
+        let first = push(&client, "first").await;
+        events.send(Event::Tickle(Tickle::Push)).unwrap();
+        match sync.next().await {
+            Some(SyncEvent::Push(push)) => assert_eq!(push.iden, first.iden),
+            other => panic!("expected push, found {:?}", other),
+        }
+        assert_eq!(sync.modified_after, first.modified);
+
+        // only pushes modified since the last fetch are retrieved
+        let second = push(&client, "second").await;
+        events.send(Event::Tickle(Tickle::Push)).unwrap();
+        match sync.next().await {
+            Some(SyncEvent::Push(push)) => assert_eq!(push.iden, second.iden),
+            other => panic!("expected push, found {:?}", other),
+        }
+        assert_eq!(sync.modified_after, second.modified);
+        assert!(sync.queued.is_empty());
+        assert!(sync.events.is_some());
+    }
+
+    #[tokio::test]
+    async fn failed_fetches_reconnect_once() {
+        let server = MockServer::start("token").unwrap();
+        let client = server.client().unwrap();
+        let (mut sync, events) = sync(&client);
+
+        server.fail_next(2, StatusCode::SERVICE_UNAVAILABLE);
+        events.send(Event::Tickle(Tickle::Push)).unwrap();
+        events.send(Event::Tickle(Tickle::Device)).unwrap();
+        // the reconnect delay outlasts this, so nothing is yielded
+        let next = tokio::time::timeout(Duration::from_millis(500), sync.next()).await;
+        assert!(next.is_err());
+
+        assert!(sync.events.is_none());
+        assert!(sync.reconnect_delay.is_some());
+        assert!(sync.pending_pushes && sync.pending_devices);
+        assert_eq!(server.requests().len(), 2);
+        assert_eq!(sync.backoff, Duration::from_secs(INITIAL_BACKOFF * 2));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn stalled_connect_reconnects() {
+        // accepts connections, but never completes the websocket handshake
+        let listener = std::net::TcpListener::bind(("127.0.0.1", 0)).unwrap();
+        let stream_url = format!("ws://{}/", listener.local_addr().unwrap());
+        let client = Client::builder("token")
+            .stream_url(&stream_url)
+            .build()
+            .unwrap();
+        let mut sync = client.sync(0.0);
+
+        let wait = Duration::from_millis(HEARTBEAT_TIMEOUT * 1000 + 500);
+        let next = tokio::time::timeout(wait, sync.next()).await;
+        assert!(next.is_err());
+
+        assert!(sync.connecting.is_none());
+        assert!(sync.reconnect_delay.is_some());
+        assert_eq!(sync.backoff, Duration::from_secs(INITIAL_BACKOFF * 2));
+    }
+}
@@ -0,0 +1,940 @@
+//! In-process stand-in for the PushBullet API, for testing code built on
+//! this crate without a PushBullet account.
+//!
+//! Only available with the `test-server` feature enabled.
+//!
+//! [`MockServer`] emulates the user, device, push, channel, file upload and
+//! OAuth token endpoints, keeping all state in memory. Identifiers and
+//! timestamps are generated deterministically, so tests behave the same on
+//! every run.
+//!
+//! Example usage:
+//!
+//! ```no_run
+//! use pb_async::testing::MockServer;
+//!
+//! # async fn run() {
+//! let server = MockServer::start("test-token").unwrap();
+//! let client = server.client().unwrap();
+//!
+//! client
+//!     .push(
+//!         pb_async::PushTarget::SelfUser {},
+//!         pb_async::PushData::Note {
+//!             title: "",
+//!             body: "Hello, user!",
+//!         },
+//!     )
+//!     .await
+//!     .unwrap();
+//!
+//! assert_eq!(server.pushes()[0].body.as_ref().unwrap(), "Hello, user!");
+//! assert_eq!(server.requests()[0].path, "/v2/pushes");
+//! # }
+//! ```
+use std::collections::HashMap;
+use std::io;
+use std::net::{SocketAddr, TcpListener};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use bytes::Bytes;
+use http::header::{HeaderMap, CONTENT_TYPE};
+use http::{request, Method, Request, Response, StatusCode};
+use http_body_util::{BodyExt, Full};
+use hyper::body::Incoming;
+use hyper::service::service_fn;
+use hyper_util::rt::TokioIo;
+use serde_json::{json, Value};
+
+use crate::{
+    https_client, Client, ClientBuilder, Device, OAuthClient, Push, StartupError, User,
+    TOKEN_HEADER,
+};
+
+type Body = Full<Bytes>;
+
+/// Timestamp of the first change made to the server state.
+const START_TIME: f64 = 1_500_000_000.0;
+/// Number of pushes returned per page when no limit is given.
+const DEFAULT_LIMIT: usize = 500;
+
+/// In-process HTTP server emulating the PushBullet API.
+///
+/// The server runs on its own `tokio` runtime in the background, and is
+/// shut down when dropped.
+///
+/// Supported endpoints:
+///
+/// - `GET users/me`
+/// - `GET devices`, `POST devices`, `POST devices/{iden}` and
+///   `DELETE devices/{iden}`
+/// - `GET pushes`, `POST pushes`, `POST pushes/{iden}`,
+///   `DELETE pushes/{iden}` and `DELETE pushes`
+/// - `GET channels`, `POST channels` and `GET channel-info`
+/// - `POST upload-request`, along with the upload and file URLs it returns
+/// - `POST ephemerals`, which has no effect
+/// - `POST oauth2/token`, exchanging codes created with
+///   [`MockServer::authorization_code`]
+///
+/// Requests to any other endpoint fail with a `404 Not Found`. Requests
+/// without the correct access token fail with a `401 Unauthorized`. Server
+/// errors can be simulated with [`MockServer::fail_next`], and rate limiting
+/// enabled with [`MockServer::set_rate_limit`].
+pub struct MockServer {
+    addr: SocketAddr,
+    token: String,
+    state: Arc<Mutex<State>>,
+    runtime: Option<tokio::runtime::Runtime>,
+}
+
+impl MockServer {
+    /// Starts a server on a random local port, accepting the given access
+    /// token.
+    ///
+    /// The server starts with a single user and no devices or pushes.
+    pub fn start(token: &str) -> io::Result<Self> {
+        let state = Arc::new(Mutex::new(State::new(token)));
+
+        let listener = TcpListener::bind(("127.0.0.1", 0))?;
+        listener.set_nonblocking(true)?;
+        let addr = listener.local_addr()?;
+        state.lock().unwrap().root = format!("http://{}/", addr);
+
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .worker_threads(1)
+            .enable_all()
+            .build()?;
+        runtime.spawn(serve(listener, state.clone()));
+
+        Ok(MockServer {
+            addr,
+            token: token.to_owned(),
+            state,
+            runtime: Some(runtime),
+        })
+    }
+
+    /// Address the server is listening on.
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// API root URL of this server, for use in [`ClientBuilder::base_url`].
+    pub fn base_url(&self) -> String {
+        format!("http://{}/v2/", self.addr)
+    }
+
+    /// Creates a builder for a client configured to use this server, with
+    /// the server's access token.
+    pub fn client_builder(&self) -> ClientBuilder {
+        Client::builder(&self.token).base_url(&self.base_url())
+    }
+
+    /// Creates a client configured to use this server, with the server's
+    /// access token.
+    pub fn client(&self) -> Result<Client, StartupError> {
+        self.client_builder().build()
+    }
+
+    /// OAuth token URL of this server, for use in
+    /// [`OAuthClient::token_url`].
+    pub fn token_url(&self) -> String {
+        format!("http://{}/oauth2/token", self.addr)
+    }
+
+    /// Creates an OAuth client configured to use this server.
+    pub fn oauth_client(
+        &self,
+        client_id: &str,
+        client_secret: &str,
+    ) -> Result<OAuthClient, StartupError> {
+        let client = OAuthClient::with_client(client_id, client_secret, https_client(false)?);
+        Ok(client.token_url(&self.token_url()))
+    }
+
+    /// Creates an authorization code, as if the user had authorized the
+    /// OAuth client with the given id. Exchanging the code with
+    /// [`OAuthClient::exchange_code`] returns the server's access token.
+    ///
+    /// Each code can only be exchanged once. The client secret isn't
+    /// checked.
+    pub fn authorization_code(&self, client_id: &str) -> String {
+        let mut state = self.state.lock().unwrap();
+        let code = state.iden("code");
+        state.oauth_codes.insert(code.clone(), client_id.to_owned());
+        code
+    }
+
+    /// Retrieves the user, as returned by [`Client::get_user`].
+    pub fn user(&self) -> User {
+        from_state(&self.state.lock().unwrap().user)
+    }
+
+    /// Adds a device, as if created with [`Client::create_device`].
+    ///
+    /// `fields` is the JSON device data to set, such as
+    /// `{"nickname": "Phone", "has_sms": true}`.
+    pub fn add_device(&self, fields: &Value) -> Device {
+        from_state(&self.state.lock().unwrap().create_device(fields))
+    }
+
+    /// Retrieves all devices, including deleted ones.
+    pub fn devices(&self) -> Vec<Device> {
+        self.state.lock().unwrap().devices.iter().map(from_state).collect()
+    }
+
+    /// Retrieves all pushes, including deleted ones, most recently created
+    /// first.
+    pub fn pushes(&self) -> Vec<Push> {
+        self.state.lock().unwrap().pushes.iter().rev().map(from_state).collect()
+    }
+
+    /// Retrieves all files which have been uploaded.
+    pub fn uploads(&self) -> Vec<Upload> {
+        self.state
+            .lock()
+            .unwrap()
+            .uploads
+            .iter()
+            .filter(|upload| upload.data.is_some())
+            .cloned()
+            .collect()
+    }
+
+    /// Retrieves all requests made to the server, in the order they were
+    /// received.
+    pub fn requests(&self) -> Vec<RecordedRequest> {
+        self.state.lock().unwrap().requests.clone()
+    }
+
+    /// Forgets all recorded requests.
+    pub fn clear_requests(&self) {
+        self.state.lock().unwrap().requests.clear();
+    }
+
+    /// Makes the next `count` API requests fail with the given status, as
+    /// if the server were having trouble. Failed requests are still
+    /// recorded, but have no other effect.
+    pub fn fail_next(&self, count: usize, status: StatusCode) {
+        let mut state = self.state.lock().unwrap();
+        state.failures = count;
+        state.failure_status = status;
+    }
+
+    /// Limits API requests to `limit` in each `period`, starting now.
+    ///
+    /// Each request uses one quota unit, and requests made once the quota is
+    /// used up fail with a `429 Too Many Requests`. Every API response
+    /// includes the rate limit headers sent by PushBullet. Unlike other
+    /// timestamps, the reset time follows the real clock.
+    pub fn set_rate_limit(&self, limit: u64, period: Duration) {
+        self.state.lock().unwrap().quota = Some(Quota {
+            limit,
+            remaining: limit,
+            period,
+            reset_at: SystemTime::now() + period,
+        });
+    }
+}
+
+impl Drop for MockServer {
+    fn drop(&mut self) {
+        // the server may be dropped within another runtime, such as in a
+        // `#[tokio::test]`, where blocking on shutdown isn't allowed
+        if let Some(runtime) = self.runtime.take() {
+            runtime.shutdown_background();
+        }
+    }
+}
+
+/// Request received by a [`MockServer`].
+#[derive(Clone, Debug)]
+pub struct RecordedRequest {
+    /// Request method.
+    pub method: Method,
+    /// Request path, such as `/v2/pushes`.
+    pub path: String,
+    /// Query string, if any.
+    pub query: Option<String>,
+    /// Request headers.
+    pub headers: HeaderMap,
+    /// Request body.
+    pub body: Bytes,
+    _priv: (),
+}
+
+impl RecordedRequest {
+    /// Parses the request body as JSON.
+    pub fn json(&self) -> Result<Value, serde_json::Error> {
+        serde_json::from_slice(&self.body)
+    }
+}
+
+/// File uploaded to a [`MockServer`], using [`Client::upload_request`].
+#[derive(Clone, Debug)]
+pub struct Upload {
+    /// File name given in the upload request.
+    pub file_name: String,
+    /// MIME type given in the upload request.
+    pub file_type: String,
+    /// URL the file can be downloaded from.
+    pub file_url: String,
+    /// Uploaded file contents, or `None` if the upload has not completed.
+    pub data: Option<Bytes>,
+    _priv: (),
+}
+
+/// Rate limit applied to API requests.
+struct Quota {
+    limit: u64,
+    remaining: u64,
+    period: Duration,
+    reset_at: SystemTime,
+}
+
+impl Quota {
+    /// Uses a quota unit, returning whether one was available.
+    fn take(&mut self) -> bool {
+        let now = SystemTime::now();
+        if now >= self.reset_at {
+            self.remaining = self.limit;
+            self.reset_at = now + self.period;
+        }
+        if self.remaining == 0 {
+            return false;
+        }
+        self.remaining -= 1;
+        true
+    }
+
+    fn add_headers(&self, headers: &mut HeaderMap) {
+        // the reset time is sent in whole seconds, rounded up so that the
+        // quota is always reset by then
+        let reset_at = self
+            .reset_at
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs_f64()
+            .ceil();
+        headers.insert("X-Ratelimit-Limit", self.limit.into());
+        headers.insert("X-Ratelimit-Remaining", self.remaining.into());
+        headers.insert("X-Ratelimit-Reset", (reset_at as u64).into());
+    }
+}
+
+/// In-memory server state. Data is kept in JSON form, as the API returns it.
+struct State {
+    token: String,
+    root: String,
+    time: f64,
+    next_iden: u32,
+    user: Value,
+    devices: Vec<Value>,
+    pushes: Vec<Value>,
+    channels: Vec<Value>,
+    uploads: Vec<Upload>,
+    /// Unused OAuth authorization codes, and the client ids they are for.
+    oauth_codes: HashMap<String, String>,
+    requests: Vec<RecordedRequest>,
+    /// Number of upcoming API requests to fail.
+    failures: usize,
+    failure_status: StatusCode,
+    /// Rate limit applied to API requests, if enabled.
+    quota: Option<Quota>,
+}
+
+impl State {
+    fn new(token: &str) -> Self {
+        State {
+            token: token.to_owned(),
+            root: String::new(),
+            time: START_TIME,
+            next_iden: 1,
+            user: json!({
+                "created": START_TIME,
+                "email": "user@example.com",
+                "email_normalized": "user@example.com",
+                "iden": "mockuser",
+                "image_url": null,
+                "max_upload_size": 26_214_400.0,
+                "modified": START_TIME,
+                "name": "Mock User",
+            }),
+            devices: Vec::new(),
+            pushes: Vec::new(),
+            channels: Vec::new(),
+            uploads: Vec::new(),
+            oauth_codes: HashMap::new(),
+            requests: Vec::new(),
+            failures: 0,
+            failure_status: StatusCode::INTERNAL_SERVER_ERROR,
+            quota: None,
+        }
+    }
+
+    /// Advances the clock, returning the new time.
+    fn tick(&mut self) -> f64 {
+        self.time += 1.0;
+        self.time
+    }
+
+    fn iden(&mut self, prefix: &str) -> String {
+        let iden = format!("{}{:08}", prefix, self.next_iden);
+        self.next_iden += 1;
+        iden
+    }
+
+    fn respond(&mut self, parts: &request::Parts, body: &[u8]) -> Response<Body> {
+        let path = parts.uri.path().trim_matches('/').to_owned();
+        let segments = path.split('/').collect::<Vec<_>>();
+
+        if segments[0] == "upload" || segments[0] == "files" {
+            return self.respond_file(&parts.method, &segments[1..], body);
+        }
+        if segments == ["oauth2", "token"] && parts.method == Method::POST {
+            return self.exchange_code(body);
+        }
+        if segments[0] != "v2" {
+            return not_found();
+        }
+        let allowed = self.quota.as_mut().is_none_or(Quota::take);
+        let mut response = if allowed {
+            self.respond_api(parts, &segments[1..], body)
+        } else {
+            error_response(
+                StatusCode::TOO_MANY_REQUESTS,
+                "ratelimited",
+                "You have been ratelimited for making too many requests to the server.",
+            )
+        };
+        if let Some(ref quota) = self.quota {
+            quota.add_headers(response.headers_mut());
+        }
+        response
+    }
+
+    fn respond_api(
+        &mut self,
+        parts: &request::Parts,
+        segments: &[&str],
+        body: &[u8],
+    ) -> Response<Body> {
+        if self.failures > 0 {
+            self.failures -= 1;
+            return error_response(
+                self.failure_status,
+                "server_error",
+                "The server is temporarily unavailable.",
+            );
+        }
+        let authorized = parts
+            .headers
+            .get(TOKEN_HEADER)
+            .is_some_and(|token| token.as_bytes() == self.token.as_bytes());
+        if !authorized {
+            return error_response(
+                StatusCode::UNAUTHORIZED,
+                "invalid_access_token",
+                "Access token is missing or invalid.",
+            );
+        }
+        let body = if body.is_empty() {
+            json!({})
+        } else {
+            match serde_json::from_slice::<Value>(body) {
+                Ok(body) => body,
+                Err(e) => {
+                    return error_response(
+                        StatusCode::BAD_REQUEST,
+                        "invalid_request",
+                        &format!("Invalid JSON body: {}", e),
+                    )
+                }
+            }
+        };
+
+        match (&parts.method, segments) {
+            (&Method::GET, ["users", "me"]) => ok(&self.user),
+            (&Method::GET, ["devices"]) => {
+                let devices = self
+                    .devices
+                    .iter()
+                    .filter(|device| device["active"] == true)
+                    .cloned()
+                    .collect::<Vec<_>>();
+                ok(&json!({ "devices": devices }))
+            }
+            (&Method::POST, ["devices"]) => ok(&self.create_device(&body)),
+            (&Method::POST, ["devices", iden]) => {
+                let now = self.tick();
+                match find_active(&mut self.devices, iden) {
+                    Some(device) => {
+                        copy_fields(device, &body, DEVICE_FIELDS);
+                        device["modified"] = json!(now);
+                        ok(device)
+                    }
+                    None => not_found(),
+                }
+            }
+            (&Method::DELETE, ["devices", iden]) => {
+                let now = self.tick();
+                match find_active(&mut self.devices, iden) {
+                    Some(device) => {
+                        device["active"] = json!(false);
+                        device["modified"] = json!(now);
+                        ok(&json!({}))
+                    }
+                    None => not_found(),
+                }
+            }
+            (&Method::GET, ["pushes"]) => self.list_pushes(parts.uri.query().unwrap_or("")),
+            (&Method::POST, ["pushes"]) => ok(&self.create_push(&body)),
+            (&Method::POST, ["pushes", iden]) => {
+                let now = self.tick();
+                match find_active(&mut self.pushes, iden) {
+                    Some(push) => {
+                        if let Some(dismissed) = body.get("dismissed") {
+                            push["dismissed"] = dismissed.clone();
+                        }
+                        push["modified"] = json!(now);
+                        ok(push)
+                    }
+                    None => not_found(),
+                }
+            }
+            (&Method::DELETE, ["pushes", iden]) => {
+                let now = self.tick();
+                match find_active(&mut self.pushes, iden) {
+                    Some(push) => {
+                        *push = deleted_push(push, now);
+                        ok(&json!({}))
+                    }
+                    None => not_found(),
+                }
+            }
+            (&Method::DELETE, ["pushes"]) => {
+                let now = self.tick();
+                for push in self.pushes.iter_mut().filter(|push| push["active"] == true) {
+                    *push = deleted_push(push, now);
+                }
+                ok(&json!({}))
+            }
+            (&Method::GET, ["channels"]) => {
+                let channels = self
+                    .channels
+                    .iter()
+                    .filter(|channel| channel["active"] == true)
+                    .cloned()
+                    .collect::<Vec<_>>();
+                ok(&json!({ "channels": channels }))
+            }
+            (&Method::POST, ["channels"]) => self.create_channel(&body),
+            (&Method::GET, ["channel-info"]) => self.channel_info(parts.uri.query().unwrap_or("")),
+            (&Method::POST, ["upload-request"]) => self.upload_request(&body),
+            // ephemerals are only delivered to the realtime event stream,
+            // which isn't emulated, so are only recorded as requests
+            (&Method::POST, ["ephemerals"]) => ok(&json!({})),
+            _ => not_found(),
+        }
+    }
+
+    fn create_device(&mut self, fields: &Value) -> Value {
+        let now = self.tick();
+        let mut device = json!({
+            "active": true,
+            "created": now,
+            "iden": self.iden("device"),
+            "modified": now,
+            "kind": "stream",
+            "type": "stream",
+        });
+        copy_fields(&mut device, fields, DEVICE_FIELDS);
+        self.devices.push(device.clone());
+        device
+    }
+
+    fn create_push(&mut self, fields: &Value) -> Value {
+        let now = self.tick();
+        let mut push = json!({
+            "iden": self.iden("push"),
+            "active": true,
+            "created": now,
+            "modified": now,
+            "dismissed": false,
+            "direction": "self",
+            "sender_iden": self.user["iden"],
+            "sender_email": self.user["email"],
+            "sender_email_normalized": self.user["email_normalized"],
+            "sender_name": self.user["name"],
+            "receiver_iden": self.user["iden"],
+            "receiver_email": self.user["email"],
+            "receiver_email_normalized": self.user["email_normalized"],
+        });
+        copy_fields(&mut push, fields, PUSH_FIELDS);
+        if let Some(device_iden) = fields.get("device_iden") {
+            push["target_device_iden"] = device_iden.clone();
+        }
+        if let Some(client_iden) = fields.get("client_iden") {
+            push["client_iden"] = client_iden.clone();
+        }
+        if let Some(email) = fields.get("email").and_then(|email| email.as_str()) {
+            if email != self.user["email"] {
+                push["direction"] = json!("outgoing");
+                push["receiver_iden"] = Value::Null;
+                push["receiver_email"] = json!(email);
+                push["receiver_email_normalized"] = json!(email.to_lowercase());
+            }
+        }
+        self.pushes.push(push.clone());
+        push
+    }
+
+    fn list_pushes(&self, query: &str) -> Response<Body> {
+        let query = url::form_urlencoded::parse(query.as_bytes())
+            .into_owned()
+            .collect::<HashMap<_, _>>();
+        let active_only = query.get("active").is_some_and(|active| active == "true");
+        let modified_after = query
+            .get("modified_after")
+            .and_then(|modified_after| modified_after.parse::<f64>().ok());
+        let limit = query
+            .get("limit")
+            .and_then(|limit| limit.parse::<usize>().ok())
+            .unwrap_or(DEFAULT_LIMIT);
+        let start = query
+            .get("cursor")
+            .and_then(|cursor| cursor.parse::<usize>().ok())
+            .unwrap_or(0);
+
+        let mut pushes = self
+            .pushes
+            .iter()
+            .filter(|push| !active_only || push["active"] == true)
+            .filter(|push| {
+                modified_after.is_none_or(|after| push["modified"].as_f64().unwrap() > after)
+            })
+            .cloned()
+            .collect::<Vec<_>>();
+        pushes.sort_by(|a, b| {
+            let a = a["modified"].as_f64().unwrap();
+            let b = b["modified"].as_f64().unwrap();
+            b.partial_cmp(&a).unwrap()
+        });
+        let remaining = pushes.split_off(start.min(pushes.len()));
+        let cursor = if remaining.len() > limit {
+            Some((start + limit).to_string())
+        } else {
+            None
+        };
+        let page = remaining.into_iter().take(limit).collect::<Vec<_>>();
+        ok(&json!({ "pushes": page, "cursor": cursor }))
+    }
+
+    fn create_channel(&mut self, fields: &Value) -> Response<Body> {
+        let tag = match fields.get("tag").and_then(|tag| tag.as_str()) {
+            Some(tag) => tag,
+            None => {
+                return error_response(StatusCode::BAD_REQUEST, "invalid_request", "Missing tag.")
+            }
+        };
+        // the error PushBullet sends for a taken tag is undocumented, so
+        // this is only a stand-in
+        if self.channels.iter().any(|channel| channel["tag"] == tag) {
+            return error_response(
+                StatusCode::BAD_REQUEST,
+                "invalid_request",
+                "Channel tag is already taken.",
+            );
+        }
+        let now = self.tick();
+        let mut channel = json!({
+            "active": true,
+            "created": now,
+            "iden": self.iden("channel"),
+            "modified": now,
+        });
+        copy_fields(&mut channel, fields, CHANNEL_FIELDS);
+        self.channels.push(channel.clone());
+        ok(&channel)
+    }
+
+    fn channel_info(&self, query: &str) -> Response<Body> {
+        let tag = url::form_urlencoded::parse(query.as_bytes())
+            .find(|(key, _)| key == "tag")
+            .map(|(_, tag)| tag.into_owned());
+        let channel = self
+            .channels
+            .iter()
+            .find(|channel| channel["active"] == true && tag.as_deref() == channel["tag"].as_str());
+        match channel {
+            Some(channel) => {
+                let mut info = json!({ "subscriber_count": 0, "recent_pushes": [] });
+                copy_fields(&mut info, channel, &["iden"]);
+                copy_fields(&mut info, channel, CHANNEL_FIELDS);
+                ok(&info)
+            }
+            None => not_found(),
+        }
+    }
+
+    fn upload_request(&mut self, body: &Value) -> Response<Body> {
+        let (file_name, file_type) = match (
+            body.get("file_name").and_then(|name| name.as_str()),
+            body.get("file_type").and_then(|kind| kind.as_str()),
+        ) {
+            (Some(file_name), Some(file_type)) => (file_name.to_owned(), file_type.to_owned()),
+            _ => {
+                return error_response(
+                    StatusCode::BAD_REQUEST,
+                    "invalid_request",
+                    "Missing file_name or file_type.",
+                )
+            }
+        };
+        let id = self.uploads.len().to_string();
+        let root = url::Url::parse(&self.root).expect("expected valid server root");
+        let mut upload_url = root.clone();
+        upload_url
+            .path_segments_mut()
+            .unwrap()
+            .pop_if_empty()
+            .extend(&["upload", &*id]);
+        let mut file_url = root;
+        file_url
+            .path_segments_mut()
+            .unwrap()
+            .pop_if_empty()
+            .extend(&["files", &*id, &*file_name]);
+
+        let upload = Upload {
+            file_name,
+            file_type,
+            file_url: file_url.into(),
+            data: None,
+            _priv: (),
+        };
+        let response = json!({
+            "file_name": upload.file_name,
+            "file_type": upload.file_type,
+            "file_url": upload.file_url,
+            "upload_url": upload_url.as_str(),
+        });
+        self.uploads.push(upload);
+        ok(&response)
+    }
+
+    /// Exchanges an OAuth authorization code for the server's access token.
+    fn exchange_code(&mut self, body: &[u8]) -> Response<Body> {
+        let body = serde_json::from_slice::<Value>(body).unwrap_or_default();
+        let field = |name: &str| body.get(name).and_then(|value| value.as_str());
+        let code = match (field("grant_type"), field("client_id"), field("code")) {
+            (Some("authorization_code"), Some(client_id), Some(code))
+                if self.oauth_codes.get(code).is_some_and(|id| id == client_id) =>
+            {
+                code.to_owned()
+            }
+            _ => {
+                return error_response(
+                    StatusCode::BAD_REQUEST,
+                    "invalid_grant",
+                    "Authorization code is invalid or has already been used.",
+                )
+            }
+        };
+        self.oauth_codes.remove(&code);
+        ok(&json!({ "access_token": self.token, "token_type": "Bearer" }))
+    }
+
+    /// Handles file uploads and downloads. These are outside the API root,
+    /// and don't require an access token.
+    fn respond_file(
+        &mut self,
+        method: &Method,
+        segments: &[&str],
+        body: &[u8],
+    ) -> Response<Body> {
+        let upload = match segments
+            .first()
+            .and_then(|id| id.parse::<usize>().ok())
+            .and_then(|id| self.uploads.get_mut(id))
+        {
+            Some(upload) => upload,
+            None => return not_found(),
+        };
+        match (method, segments.len()) {
+            (&Method::POST, 1) => {
+                upload.data = Some(multipart_file(body).into());
+                Response::builder()
+                    .status(StatusCode::NO_CONTENT)
+                    .body(Body::default())
+                    .unwrap()
+            }
+            (&Method::GET, 2) => match upload.data {
+                Some(ref data) => Response::builder()
+                    .header(CONTENT_TYPE, &*upload.file_type)
+                    .body(Body::from(data.clone()))
+                    .unwrap(),
+                None => not_found(),
+            },
+            _ => not_found(),
+        }
+    }
+}
+
+/// Device fields which can be set when creating or updating a device.
+static DEVICE_FIELDS: &[&str] = &[
+    "nickname",
+    "model",
+    "manufacturer",
+    "push_token",
+    "app_version",
+    "icon",
+    "has_sms",
+];
+
+/// Push fields which are copied from the push creation request.
+static PUSH_FIELDS: &[&str] = &[
+    "type",
+    "title",
+    "body",
+    "url",
+    "file_name",
+    "file_type",
+    "file_url",
+    "guid",
+    "source_device_iden",
+];
+
+/// Channel fields which are copied from the channel creation request.
+static CHANNEL_FIELDS: &[&str] = &[
+    "tag",
+    "name",
+    "description",
+    "image_url",
+    "website_url",
+    "feed_url",
+    "feed_filters",
+];
+
+/// Accepts connections until the runtime is shut down.
+async fn serve(listener: TcpListener, state: Arc<Mutex<State>>) {
+    let listener = match tokio::net::TcpListener::from_std(listener) {
+        Ok(listener) => listener,
+        Err(e) => return error!("mock server error: {}", e),
+    };
+    loop {
+        let (stream, _addr) = match listener.accept().await {
+            Ok(accepted) => accepted,
+            Err(e) => {
+                error!("mock server error: {}", e);
+                continue;
+            }
+        };
+        let state = state.clone();
+        tokio::spawn(async move {
+            let service = service_fn(move |request| handle(state.clone(), request));
+            let connection = hyper::server::conn::http1::Builder::new()
+                .serve_connection(TokioIo::new(stream), service);
+            if let Err(e) = connection.await {
+                error!("mock server error: {}", e);
+            }
+        });
+    }
+}
+
+async fn handle(
+    state: Arc<Mutex<State>>,
+    request: Request<Incoming>,
+) -> Result<Response<Body>, hyper::Error> {
+    let (parts, body) = request.into_parts();
+    let body = body.collect().await?.to_bytes();
+    let mut state = state.lock().unwrap();
+    state.requests.push(RecordedRequest {
+        method: parts.method.clone(),
+        path: parts.uri.path().to_owned(),
+        query: parts.uri.query().map(|query| query.to_owned()),
+        headers: parts.headers.clone(),
+        body: body.clone(),
+        _priv: (),
+    });
+    Ok(state.respond(&parts, &body))
+}
+
+fn from_state<T: serde::de::DeserializeOwned>(value: &Value) -> T {
+    serde_json::from_value(value.clone()).expect("expected mock server state to be valid")
+}
+
+fn find_active<'a>(items: &'a mut [Value], iden: &str) -> Option<&'a mut Value> {
+    items
+        .iter_mut()
+        .find(|item| item["iden"] == iden && item["active"] == true)
+}
+
+fn copy_fields(target: &mut Value, source: &Value, fields: &[&str]) {
+    for field in fields {
+        if let Some(value) = source.get(*field) {
+            target[*field] = value.clone();
+        }
+    }
+}
+
+fn deleted_push(push: &Value, now: f64) -> Value {
+    json!({
+        "iden": push["iden"],
+        "active": false,
+        "created": push["created"],
+        "modified": now,
+    })
+}
+
+/// Extracts the contents of the first part of a multipart body, or returns
+/// the body as-is if it isn't multipart.
+fn multipart_file(body: &[u8]) -> Vec<u8> {
+    let extracted = find(body, b"\r\n\r\n").and_then(|start| {
+        let start = start + 4;
+        // the boundary is the first line of the body
+        let boundary_end = find(body, b"\r\n")?;
+        let mut delimiter = b"\r\n".to_vec();
+        delimiter.extend_from_slice(&body[..boundary_end]);
+        let end = find(&body[start..], &delimiter)?;
+        Some(body[start..start + end].to_vec())
+    });
+    match extracted {
+        Some(file) if body.starts_with(b"--") => file,
+        _ => body.to_vec(),
+    }
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+fn ok(value: &Value) -> Response<Body> {
+    Response::builder()
+        .header(CONTENT_TYPE, "application/json")
+        .body(Body::from(serde_json::to_vec(value).unwrap()))
+        .unwrap()
+}
+
+fn error_response(status: StatusCode, code: &str, message: &str) -> Response<Body> {
+    let body = json!({
+        "error": {
+            "cat": "~(=^‥^)",
+            "code": code,
+            "type": "invalid_request",
+            "message": message,
+        },
+    });
+    Response::builder()
+        .status(status)
+        .header(CONTENT_TYPE, "application/json")
+        .body(Body::from(serde_json::to_vec(&body).unwrap()))
+        .unwrap()
+}
+
+fn not_found() -> Response<Body> {
+    error_response(StatusCode::NOT_FOUND, "not_found", "Object not found.")
+}
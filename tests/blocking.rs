@@ -0,0 +1,106 @@
+#![cfg(all(feature = "blocking", feature = "test-server"))]
+use std::io::Cursor;
+
+use http::StatusCode;
+use pb_async::testing::MockServer;
+use pb_async::{blocking, ListPushes, PushData, PushTarget, RequestError, ServerErrorCode};
+
+fn client(server: &MockServer) -> blocking::Client {
+    blocking::Client::from_client(server.client().unwrap()).unwrap()
+}
+
+#[test]
+fn get_user() {
+    let server = MockServer::start("token").unwrap();
+    let client = client(&server);
+
+    let user = client.get_user().unwrap();
+    assert_eq!(user.iden, server.user().iden);
+    assert_eq!(server.requests()[0].path, "/v2/users/me");
+}
+
+#[test]
+fn invalid_token() {
+    let server = MockServer::start("token").unwrap();
+    let client = pb_async::Client::builder("wrong-token")
+        .base_url(&server.base_url())
+        .build()
+        .unwrap();
+    let client = blocking::Client::from_client(client).unwrap();
+
+    match client.get_user() {
+        Err(RequestError::Server {
+            status: StatusCode::UNAUTHORIZED,
+            code: ServerErrorCode::InvalidAccessToken,
+            cat: Some(_),
+            ..
+        }) => {}
+        other => panic!("expected invalid token error, found {:?}", other),
+    }
+}
+
+#[test]
+fn pushes() {
+    let server = MockServer::start("token").unwrap();
+    let client = client(&server);
+
+    for body in &["first", "second", "third"] {
+        client
+            .push(PushTarget::SelfUser {}, PushData::Note { title: "", body })
+            .unwrap();
+    }
+
+    let bodies = client
+        .all_pushes(ListPushes {
+            limit: Some(2),
+            ..Default::default()
+        })
+        .map(|push| push.unwrap().body.unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(bodies, ["third", "second", "first"]);
+}
+
+#[test]
+fn upload() {
+    let server = MockServer::start("token").unwrap();
+    let client = client(&server);
+
+    let response = client
+        .upload_request("hello.txt", "text/plain", "Hello, world!")
+        .unwrap();
+
+    let uploads = server.uploads();
+    assert_eq!(uploads[0].file_url, response.file_url);
+    assert_eq!(&uploads[0].data.as_ref().unwrap()[..], b"Hello, world!");
+}
+
+#[test]
+fn upload_stream() {
+    let server = MockServer::start("token").unwrap();
+    let client = client(&server);
+
+    let data = vec![b'x'; 200 * 1024];
+    let response = client
+        .upload_request_stream(
+            "data.bin",
+            "application/octet-stream",
+            Cursor::new(data.clone()),
+        )
+        .unwrap();
+
+    let uploads = server.uploads();
+    assert_eq!(uploads[0].file_url, response.file_url);
+    assert_eq!(uploads[0].data.as_ref().unwrap()[..], data[..]);
+}
+
+#[test]
+fn exchange_code() {
+    let server = MockServer::start("token").unwrap();
+    let oauth_client = server.oauth_client("client-id", "client-secret").unwrap();
+    let oauth_client = blocking::OAuthClient::from_client(oauth_client).unwrap();
+
+    let code = server.authorization_code("client-id");
+    let token = oauth_client.exchange_code(&code).unwrap();
+    assert_eq!(token.access_token, "token");
+    assert_eq!(server.requests()[0].path, "/oauth2/token");
+}
@@ -0,0 +1,366 @@
+#![cfg(feature = "test-server")]
+use std::time::Duration;
+
+use bytes::Bytes;
+use futures_util::{stream, TryStreamExt};
+use http::StatusCode;
+use pb_async::testing::MockServer;
+use pb_async::{
+    ChannelParams, DeviceIcon, DeviceParams, ListPushes, PushData, PushTarget, RequestError,
+    RetryPolicy, ServerErrorCode,
+};
+
+#[tokio::test]
+async fn get_user() {
+    let server = MockServer::start("token").unwrap();
+    let client = server.client().unwrap();
+
+    let user = client.get_user().await.unwrap();
+    assert_eq!(user.iden, server.user().iden);
+
+    let requests = server.requests();
+    assert_eq!(requests.len(), 1);
+    assert_eq!(requests[0].path, "/v2/users/me");
+    assert_eq!(requests[0].headers["Access-Token"], "token");
+}
+
+#[tokio::test]
+async fn invalid_token() {
+    let server = MockServer::start("token").unwrap();
+    let client = pb_async::Client::builder("wrong-token")
+        .base_url(&server.base_url())
+        .build()
+        .unwrap();
+
+    match client.get_user().await {
+        Err(RequestError::Server {
+            status: StatusCode::UNAUTHORIZED,
+            code: ServerErrorCode::InvalidAccessToken,
+            cat: Some(_),
+            ..
+        }) => {}
+        other => panic!("expected invalid token error, found {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn devices() {
+    let server = MockServer::start("token").unwrap();
+    let client = server.client().unwrap();
+
+    let device = client
+        .create_device(&DeviceParams {
+            nickname: Some("Test"),
+            icon: Some(&DeviceIcon::Phone),
+            has_sms: Some(true),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+    assert_eq!(device.nickname.as_ref().unwrap(), "Test");
+    assert_eq!(device.icon, Some(DeviceIcon::Phone));
+    assert!(device.has_sms);
+
+    let devices = client.list_devices().await.unwrap();
+    assert_eq!(devices.len(), 1);
+    assert_eq!(devices[0].iden, device.iden);
+
+    client.delete_device(&device.iden).await.unwrap();
+    assert!(client.list_devices().await.unwrap().is_empty());
+    assert!(!server.devices()[0].active);
+}
+
+#[tokio::test]
+async fn pushes() {
+    let server = MockServer::start("token").unwrap();
+    let client = server.client().unwrap();
+
+    for body in &["first", "second", "third"] {
+        client
+            .push(PushTarget::SelfUser {}, PushData::Note { title: "", body })
+            .await
+            .unwrap();
+    }
+    let first = server.pushes().pop().unwrap();
+    assert_eq!(first.body.as_ref().unwrap(), "first");
+
+    let dismissed = client.dismiss_push(&first.iden).await.unwrap();
+    assert!(dismissed.dismissed);
+    assert!(dismissed.modified > first.modified);
+
+    // paging follows the cursor, most recently modified first
+    let pushes: Vec<_> = client
+        .all_pushes(ListPushes {
+            limit: Some(2),
+            ..Default::default()
+        })
+        .try_collect()
+        .await
+        .unwrap();
+    let bodies = pushes
+        .iter()
+        .map(|push| push.body.as_ref().unwrap().as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(bodies, ["first", "third", "second"]);
+
+    client.delete_all_pushes().await.unwrap();
+    let page = client
+        .list_pushes(&ListPushes {
+            active: Some(true),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+    assert!(page.pushes.is_empty());
+}
+
+#[tokio::test]
+async fn channels() {
+    let server = MockServer::start("token").unwrap();
+    let client = server.client().unwrap();
+
+    let params = ChannelParams {
+        tag: "deployments",
+        name: "Deployments",
+        description: "Deployment announcements",
+        ..Default::default()
+    };
+    let channel = client.create_channel(&params).await.unwrap();
+    assert_eq!(channel.tag, "deployments");
+
+    let info = client.channel_info("deployments").await.unwrap();
+    assert_eq!(info.iden, channel.iden);
+    assert_eq!(info.name, "Deployments");
+
+    match client.create_channel(&params).await {
+        Err(RequestError::Server { .. }) => {}
+        other => panic!("expected server error, found {:?}", other),
+    }
+    let channels = client.list_channels().await.unwrap();
+    assert_eq!(channels.len(), 1);
+    assert_eq!(channels[0].iden, channel.iden);
+}
+
+#[tokio::test]
+async fn upload() {
+    let server = MockServer::start("token").unwrap();
+    let client = server.client().unwrap();
+
+    let response = client
+        .upload_request("hello.txt", "text/plain", "Hello, world!")
+        .await
+        .unwrap();
+
+    let uploads = server.uploads();
+    assert_eq!(uploads.len(), 1);
+    assert_eq!(uploads[0].file_name, "hello.txt");
+    assert_eq!(uploads[0].file_url, response.file_url);
+    assert_eq!(&uploads[0].data.as_ref().unwrap()[..], b"Hello, world!");
+}
+
+#[tokio::test]
+async fn upload_stream() {
+    let server = MockServer::start("token").unwrap();
+    let client = server.client().unwrap();
+
+    let chunks = ["Hello, ", "streamed ", "world!"]
+        .into_iter()
+        .map(|chunk| Ok::<_, std::io::Error>(Bytes::from(chunk)));
+    let response = client
+        .upload_request_stream("hello.txt", "text/plain", stream::iter(chunks))
+        .await
+        .unwrap();
+
+    let uploads = server.uploads();
+    assert_eq!(uploads[0].file_url, response.file_url);
+    assert_eq!(
+        &uploads[0].data.as_ref().unwrap()[..],
+        b"Hello, streamed world!"
+    );
+}
+
+#[tokio::test]
+async fn ephemerals() {
+    let server = MockServer::start("token").unwrap();
+    let client = server.client().unwrap();
+
+    client.send_clipboard("first", "device").await.unwrap();
+    let clone = client.clone();
+    clone.send_clipboard("second", "device").await.unwrap();
+
+    // the user is only retrieved once, and shared between clones
+    let requests = server.requests();
+    let paths = requests
+        .iter()
+        .map(|request| request.path.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(paths, ["/v2/users/me", "/v2/ephemerals", "/v2/ephemerals"]);
+    let push = &requests[2].json().unwrap()["push"];
+    assert_eq!(push["body"], "second");
+    assert_eq!(push["source_user_iden"], server.user().iden.as_str());
+}
+
+#[tokio::test]
+async fn exchange_code() {
+    let server = MockServer::start("token").unwrap();
+    let oauth_client = server.oauth_client("client-id", "client-secret").unwrap();
+
+    let code = server.authorization_code("client-id");
+    let token = oauth_client.exchange_code(&code).await.unwrap();
+    assert_eq!(token.access_token, "token");
+    assert_eq!(token.token_type, "Bearer");
+
+    let requests = server.requests();
+    assert_eq!(requests[0].path, "/oauth2/token");
+    let body = requests[0].json().unwrap();
+    assert_eq!(body["grant_type"], "authorization_code");
+    assert_eq!(body["client_id"], "client-id");
+    assert_eq!(body["client_secret"], "client-secret");
+    assert_eq!(body["code"], code.as_str());
+
+    // codes can only be used once
+    match oauth_client.exchange_code(&code).await {
+        Err(RequestError::Server {
+            status: StatusCode::BAD_REQUEST,
+            ..
+        }) => {}
+        other => panic!("expected invalid grant error, found {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn user_agent() {
+    let server = MockServer::start("token").unwrap();
+    let client = server
+        .client_builder()
+        .user_agent("pb-async-tests")
+        .timeout(Duration::from_secs(10))
+        .build()
+        .unwrap();
+
+    client.get_user().await.unwrap();
+    assert_eq!(server.requests()[0].headers["User-Agent"], "pb-async-tests");
+}
+
+fn retry_policy() -> RetryPolicy {
+    RetryPolicy::new()
+        .base_delay(Duration::from_millis(1))
+        .jitter(false)
+}
+
+#[tokio::test]
+async fn retry_get() {
+    let server = MockServer::start("token").unwrap();
+    let client = server
+        .client_builder()
+        .retry_policy(retry_policy())
+        .build()
+        .unwrap();
+
+    server.fail_next(2, StatusCode::SERVICE_UNAVAILABLE);
+    client.get_user().await.unwrap();
+    assert_eq!(server.requests().len(), 3);
+
+    server.clear_requests();
+    server.fail_next(3, StatusCode::SERVICE_UNAVAILABLE);
+    match client.get_user().await {
+        Err(RequestError::Server {
+            status: StatusCode::SERVICE_UNAVAILABLE,
+            code: ServerErrorCode::Other(ref code),
+            ..
+        }) if code == "server_error" => {}
+        other => panic!("expected server error, found {:?}", other),
+    }
+    assert_eq!(server.requests().len(), 3);
+}
+
+#[tokio::test]
+async fn retry_push() {
+    let server = MockServer::start("token").unwrap();
+    let client = server
+        .client_builder()
+        .retry_policy(retry_policy())
+        .build()
+        .unwrap();
+
+    server.fail_next(1, StatusCode::BAD_GATEWAY);
+    let push = client
+        .push(
+            PushTarget::SelfUser {},
+            PushData::Note {
+                title: "",
+                body: "retried",
+            },
+        )
+        .await
+        .unwrap();
+
+    // both attempts carry the same guid, so the server can ignore duplicates
+    let requests = server.requests();
+    assert_eq!(requests.len(), 2);
+    let guid = requests[0].json().unwrap()["guid"].clone();
+    assert!(guid.is_string());
+    assert_eq!(requests[1].json().unwrap()["guid"], guid);
+    assert_eq!(push.guid.as_ref().unwrap(), guid.as_str().unwrap());
+}
+
+#[tokio::test]
+async fn no_retry_post() {
+    let server = MockServer::start("token").unwrap();
+    let client = server
+        .client_builder()
+        .retry_policy(retry_policy())
+        .build()
+        .unwrap();
+
+    server.fail_next(1, StatusCode::SERVICE_UNAVAILABLE);
+    let result = client
+        .create_device(&DeviceParams {
+            nickname: Some("Test"),
+            ..Default::default()
+        })
+        .await;
+    assert!(result.is_err());
+    assert_eq!(server.requests().len(), 1);
+}
+
+#[tokio::test]
+async fn rate_limit() {
+    let server = MockServer::start("token").unwrap();
+    let client = server.client().unwrap();
+    assert!(client.rate_limit().is_none());
+
+    server.set_rate_limit(2, Duration::from_secs(60));
+    client.get_user().await.unwrap();
+    let rate_limit = client.rate_limit().unwrap();
+    assert_eq!(rate_limit.limit, 2);
+    assert_eq!(rate_limit.remaining, 1);
+    assert!(rate_limit.reset_in() > Duration::from_secs(30));
+
+    client.get_user().await.unwrap();
+    assert!(client.rate_limit().unwrap().is_exhausted());
+    match client.get_user().await {
+        Err(RequestError::RateLimited {
+            reset_at: Some(reset_at),
+        }) => assert_eq!(reset_at, rate_limit.reset_at),
+        other => panic!("expected rate limited error, found {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn wait_for_rate_limit() {
+    let server = MockServer::start("token").unwrap();
+    let client = server
+        .client_builder()
+        .wait_for_rate_limit(Duration::from_secs(10))
+        .build()
+        .unwrap();
+
+    server.set_rate_limit(1, Duration::from_millis(500));
+    client.get_user().await.unwrap();
+    assert!(client.rate_limit().unwrap().is_exhausted());
+
+    // the second request waits for the quota to reset rather than failing
+    client.get_user().await.unwrap();
+    assert_eq!(server.requests().len(), 2);
+}